@@ -0,0 +1,65 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pixelflut::sync::PixelflutClient;
+use pixelflut::{Command, Pixel};
+use std::io::{self, BufWriter, Read, Write};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+/// Stream that discards everything written to it.
+struct NullStream;
+
+impl Read for NullStream {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for NullStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        black_box(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn sync_client(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sync_client");
+    group.throughput(Throughput::Elements(u64::from(WIDTH * HEIGHT)));
+
+    // the previous client path: `Display` formatting into a buffered writer
+    group.bench_function("write_fmt", |b| {
+        let mut stream = BufWriter::new(NullStream);
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    let pixel = Pixel::new((x, y).into(), (255, 0, 10).into());
+                    stream
+                        .write_fmt(format_args!("{}\n", Command::Px(pixel)))
+                        .unwrap();
+                }
+            }
+            stream.flush().unwrap();
+        })
+    });
+
+    group.bench_function("pixel_buffer", |b| {
+        let mut client = PixelflutClient::new(NullStream);
+        b.iter(|| {
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    client.set(x, y, (255, 0, 10)).unwrap();
+                }
+            }
+            client.flush().unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, sync_client);
+criterion_main!(benches);
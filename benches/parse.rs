@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pixelflut::{Color, Command, Coordinate, Pixel};
+use std::str::FromStr;
+
+static LINES: &[&[u8]] = &[
+    b"PX 12 34 ff000a\n",
+    b"PX 1919 1079 00ff0a\n",
+    b"PX 640 480 11223344\n",
+    b"PX 7 8\n",
+    b"SIZE\n",
+];
+
+/// The string based parser used before `from_bytes`, kept as the baseline.
+///
+/// It splits with `split_whitespace` and parses with `str::parse` and `from_str_radix`.
+fn parse_str_baseline(s: &str) -> Option<Command> {
+    fn coordinate<'a>(iter: &mut impl Iterator<Item = &'a str>) -> Option<Coordinate> {
+        Some(Coordinate::new(
+            iter.next()?.parse().ok()?,
+            iter.next()?.parse().ok()?,
+        ))
+    }
+
+    fn color(s: &str) -> Option<Color> {
+        let byte = |range| u8::from_str_radix(s.get(range)?, 16).ok();
+        match s.len() {
+            6 => Some(Color::rgb(byte(0..2)?, byte(2..4)?, byte(4..6)?)),
+            8 => Some(Color::rgba(
+                byte(0..2)?,
+                byte(2..4)?,
+                byte(4..6)?,
+                byte(6..8)?,
+            )),
+            _ => None,
+        }
+    }
+
+    let mut iter = s.split_whitespace();
+    let command = match iter.next()? {
+        "PX" => {
+            let position = coordinate(&mut iter)?;
+            match iter.next() {
+                Some(c) => Command::Px(Pixel::new(position, color(c)?)),
+                None => Command::GetPx(position),
+            }
+        }
+        "OFFSET" => Command::Offset(coordinate(&mut iter)?),
+        "SIZE" => Command::Size,
+        "HELP" => Command::Help,
+        _ => return None,
+    };
+    match iter.next() {
+        None => Some(command),
+        Some(_) => None,
+    }
+}
+
+fn parse(c: &mut Criterion) {
+    for line in LINES {
+        let baseline = parse_str_baseline(std::str::from_utf8(line).unwrap());
+        assert_eq!(baseline, Command::from_bytes(line).ok());
+    }
+
+    let mut group = c.benchmark_group("parse_command");
+    group.throughput(Throughput::Elements(LINES.len() as u64));
+
+    group.bench_function("str_baseline", |b| {
+        b.iter(|| {
+            for line in LINES {
+                let line = std::str::from_utf8(black_box(line)).unwrap();
+                black_box(parse_str_baseline(line).unwrap());
+            }
+        })
+    });
+
+    // UTF-8 validation followed by `FromStr`, which shares the parser with `from_bytes`
+    group.bench_function("from_str", |b| {
+        b.iter(|| {
+            for line in LINES {
+                let line = std::str::from_utf8(black_box(line)).unwrap();
+                black_box(Command::from_str(line).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("from_bytes", |b| {
+        b.iter(|| {
+            for line in LINES {
+                black_box(Command::from_bytes(black_box(line)).unwrap());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -4,6 +4,7 @@ extern crate tokio;
 
 use clap::Clap;
 use pixelflut::sync::PixelflutClient;
+use pixelflut::{Painter, Rect};
 use std::net::SocketAddr;
 
 #[derive(Clap)]
@@ -20,11 +21,9 @@ fn main() -> anyhow::Result<()> {
     let (width, height) = client.dimensions()?;
     println!("Size: {}x{}", width, height);
 
-    for h in 0..height {
-        for w in 0..width {
-            client.set(w, h, (255, 255, 255))?;
-        }
-    }
+    let painter = Painter::new((width, height));
+    client
+        .set_pixels(painter.fill_rect(Rect::from_dimensions((width, height)), (255, 255, 255)))?;
     client.flush()?;
 
     Ok(())
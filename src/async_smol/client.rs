@@ -0,0 +1,33 @@
+use futures_io::{AsyncRead, AsyncWrite};
+use smol::net::{AsyncToSocketAddrs, TcpStream};
+
+use crate::async_smol::transport::FuturesTransport;
+use crate::async_wrappers::client_wrapper;
+
+client_wrapper! {
+    transport: FuturesTransport,
+    io: AsyncRead, AsyncWrite,
+    tcp_stream: TcpStream,
+    to_socket_addrs: AsyncToSocketAddrs,
+
+    /// Async Pixelflut client.
+    ///
+    /// The client can be used with any transport implementing
+    /// [`AsyncRead`] and [`AsyncWrite`], e.g. an async-std `TcpStream` or a TLS stream.
+    /// Use [`connect`](PixelflutClient::connect) for a plain TCP connection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pixelflut::async_smol::PixelflutClient;
+    ///
+    /// fn main() -> pixelflut::PixelflutResult<()> {
+    ///     smol::block_on(async {
+    ///         let mut client = PixelflutClient::connect("127.0.0.1:1337").await?;
+    ///         client.set(1, 2, (255, 0, 0)).await?;
+    ///         client.flush().await
+    ///     })
+    /// }
+    /// ```
+    pub struct PixelflutClient;
+}
@@ -0,0 +1,12 @@
+//! The async implementation of pixelflut for smol and async-std.
+//!
+//! The client and server streams work with any transport implementing the
+//! [`futures-io`](futures_io) traits, e.g. the `TcpStream`s of smol and async-std.
+//! [`PixelflutClient::connect`] and [`PixelflutServer`] use smol's networking.
+mod client;
+mod server;
+mod transport;
+
+pub use crate::connection::SERVER_READ_BUFFER_DEFAULT_CAPACITY;
+pub use client::PixelflutClient;
+pub use server::{PixelflutServer, PixelflutServerStream};
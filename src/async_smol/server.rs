@@ -0,0 +1,53 @@
+use futures_core::Future;
+use futures_io::{AsyncRead, AsyncWrite};
+use smol::net::{AsyncToSocketAddrs, TcpListener, TcpStream};
+
+use crate::async_smol::transport::FuturesTransport;
+use crate::async_wrappers::server_wrappers;
+
+server_wrappers! {
+    module: "async_smol",
+    net: "smol::net",
+    transport: FuturesTransport,
+    io: AsyncRead, AsyncWrite,
+    listener: TcpListener,
+    tcp_stream: TcpStream,
+    to_socket_addrs: AsyncToSocketAddrs,
+    spawn: spawn_detached,
+
+    /// Async Pixelflut server connection.
+    ///
+    /// The stream can be used with any transport implementing
+    /// [`AsyncRead`] and [`AsyncWrite`], e.g. the `TcpStream` of smol or async-std.
+    pub struct PixelflutServerStream;
+
+    /// Async Pixelflut server drawing on a shared [`Canvas`].
+    ///
+    /// Every connection is handled in its own task on smol's global executor.
+    /// `SIZE` is answered with the dimensions of the canvas
+    /// and `PX x y` with the current color of the pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pixelflut::async_smol::PixelflutServer;
+    /// use pixelflut::Canvas;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() -> pixelflut::PixelflutResult<()> {
+    ///     smol::block_on(async {
+    ///         let canvas = Arc::new(Canvas::new(800, 600));
+    ///         let server = PixelflutServer::bind("127.0.0.1:1337", canvas).await?;
+    ///         server.run().await
+    ///     })
+    /// }
+    /// ```
+    ///
+    /// [`Canvas`]: crate::Canvas
+    pub struct PixelflutServer;
+}
+
+/// Runs a connection on smol's global executor without waiting for it.
+fn spawn_detached(future: impl Future<Output = ()> + Send + 'static) {
+    smol::spawn(future).detach();
+}
@@ -0,0 +1,60 @@
+use bytes::BytesMut;
+use futures_io::{AsyncRead, AsyncWrite};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::connection::Transport;
+
+/// Adapts a `futures-io` stream to the shared connection logic.
+pub(crate) struct FuturesTransport<S> {
+    pub(crate) stream: S,
+    /// Maximum number of bytes that are read at once.
+    read_capacity: usize,
+    /// Initialized memory the stream reads into, allocated on the first read.
+    read_buf: Vec<u8>,
+}
+
+impl<S> FuturesTransport<S> {
+    /// Creates a transport reading up to `read_capacity` bytes at once,
+    /// like the read buffer of the connection.
+    pub(crate) fn new(stream: S, read_capacity: usize) -> FuturesTransport<S> {
+        FuturesTransport {
+            stream,
+            // a zero length read would look like the end of the stream
+            read_capacity: read_capacity.max(1),
+            read_buf: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Transport for FuturesTransport<S> {
+    fn poll_read_buf(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut BytesMut,
+    ) -> Poll<io::Result<usize>> {
+        // futures-io only reads into initialized memory,
+        // so the zeroed buffer is kept instead of filling `buf` on every read
+        if self.read_buf.is_empty() {
+            self.read_buf = vec![0; self.read_capacity];
+        }
+        let result = Pin::new(&mut self.stream).poll_read(cx, &mut self.read_buf);
+        if let Poll::Ready(Ok(read)) = result {
+            buf.extend_from_slice(&self.read_buf[..read]);
+        }
+        result
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
@@ -0,0 +1,175 @@
+//! `tokio-util` codecs for framing Pixelflut streams.
+use bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+
+pub use crate::codec::{PixelflutClientCodec, PixelflutServerCodec};
+use crate::command::{Command, Response};
+use crate::error::{PixelflutError, PixelflutResult};
+
+impl Decoder for PixelflutServerCodec {
+    type Item = Command;
+    type Error = PixelflutError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> PixelflutResult<Option<Command>> {
+        self.decode_command(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> PixelflutResult<Option<Command>> {
+        self.decode_command_eof(src)
+    }
+}
+
+impl Encoder<&Response> for PixelflutServerCodec {
+    type Error = PixelflutError;
+
+    fn encode(&mut self, response: &Response, dst: &mut BytesMut) -> PixelflutResult<()> {
+        self.encode_response(response, dst)
+    }
+}
+
+impl Encoder<Response> for PixelflutServerCodec {
+    type Error = PixelflutError;
+
+    fn encode(&mut self, response: Response, dst: &mut BytesMut) -> PixelflutResult<()> {
+        self.encode_response(&response, dst)
+    }
+}
+
+impl Decoder for PixelflutClientCodec {
+    type Item = Response;
+    type Error = PixelflutError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> PixelflutResult<Option<Response>> {
+        self.decode_response(src)
+    }
+}
+
+impl Encoder<&Command> for PixelflutClientCodec {
+    type Error = PixelflutError;
+
+    fn encode(&mut self, command: &Command, dst: &mut BytesMut) -> PixelflutResult<()> {
+        self.encode_command(command, dst)
+    }
+}
+
+impl Encoder<Command> for PixelflutClientCodec {
+    type Error = PixelflutError;
+
+    fn encode(&mut self, command: Command, dst: &mut BytesMut) -> PixelflutResult<()> {
+        self.encode_command(&command, dst)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use crate::async_tokio::{PixelflutClientCodec, PixelflutServerCodec};
+    use crate::{Command, CustomCommand, Pixel, PixelEncoding, PixelflutErrorKind, Response};
+
+    #[test]
+    fn server_decode() {
+        let mut codec = PixelflutServerCodec::new();
+        let mut buf = BytesMut::from(&b"FOO 1\nPB\x01\x00\x0a\x00\x0a\x0a\x0a\xffSIZE\n"[..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Command::Custom(Box::new(CustomCommand::new(
+                "FOO".to_string(),
+                vec!["1".to_string()]
+            ))))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Command::Px(Pixel::new((1, 10).into(), (10, 10, 10).into())))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Command::Size));
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn server_decode_errors() {
+        let mut codec = PixelflutServerCodec::new();
+        let mut buf = BytesMut::from(&b"PX 1\nSIZE\nPX 1 2"[..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap_err().kind(),
+            PixelflutErrorKind::WrongNumberOfArguments
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Command::Size));
+        assert_eq!(
+            codec.decode_eof(&mut buf).unwrap_err().kind(),
+            PixelflutErrorKind::Io
+        );
+
+        let mut buf = BytesMut::from(&[b'0'; 64][..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap_err().kind(),
+            PixelflutErrorKind::Io
+        );
+        codec.set_max_line_length(128);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        // complete lines are limited as well, independent of how they were received
+        let mut codec = PixelflutServerCodec::new();
+        let mut buf =
+            BytesMut::from(&b"TEXT 1 2 the quick brown fox jumps over the lazy dog\nSIZE\n"[..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap_err().kind(),
+            PixelflutErrorKind::Io
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(Command::Size));
+    }
+
+    #[test]
+    fn server_encode() {
+        let mut codec = PixelflutServerCodec::new();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Response::Size { w: 8, h: 6 }, &mut buf)
+            .unwrap();
+        codec.encode(&Response::Help("a".into()), &mut buf).unwrap();
+        assert_eq!(&buf[..], b"SIZE 8 6\nHELP a\nHELP\n");
+    }
+
+    #[test]
+    fn client_encode() {
+        let mut codec = PixelflutClientCodec::new();
+        let mut buf = BytesMut::new();
+        let pixel = Pixel::new((1, 2).into(), (255, 0, 0).into());
+        codec.encode(Command::Px(pixel), &mut buf).unwrap();
+        codec.set_encoding(PixelEncoding::Binary);
+        codec.encode(Command::Px(pixel), &mut buf).unwrap();
+        codec
+            .encode(&Command::Offset((3, 4).into()), &mut buf)
+            .unwrap();
+        assert_eq!(
+            &buf[..],
+            b"PX 1 2 ff0000\nPB\x01\x00\x02\x00\xff\x00\x00\xffOFFSET 3 4\n"
+        );
+    }
+
+    #[test]
+    fn client_decode() {
+        let mut codec = PixelflutClientCodec::new();
+        let mut buf = BytesMut::from(&b"SIZE 8 6\r\nHELP a\nHELP b\nHE"[..]);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Response::Size { w: 8, h: 6 })
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b"LP\nHELP\nERROR out of range\n");
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Response::Help("a\nb".into()))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Response::Help("".into()))
+        );
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Response::Error("out of range".into()))
+        );
+        assert!(buf.is_empty());
+    }
+}
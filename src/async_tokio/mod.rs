@@ -1,6 +1,10 @@
 //! The async Tokio implementation of pixelflut.
 mod client;
+mod codec;
 mod server;
+mod transport;
 
+pub use crate::connection::SERVER_READ_BUFFER_DEFAULT_CAPACITY;
 pub use client::PixelflutClient;
-pub use server::PixelflutServerStream;
+pub use codec::{PixelflutClientCodec, PixelflutServerCodec};
+pub use server::{PixelflutServer, PixelflutServerStream};
@@ -0,0 +1,43 @@
+use bytes::BytesMut;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::io::poll_read_buf;
+
+use crate::connection::Transport;
+
+/// Adapts a Tokio stream to the shared connection logic.
+pub(crate) struct TokioTransport<S> {
+    pub(crate) stream: S,
+}
+
+impl<S> TokioTransport<S> {
+    /// Tokio reads directly into the read buffer of the connection,
+    /// so the transport does not need a read capacity of its own.
+    pub(crate) fn new(stream: S, _read_capacity: usize) -> TokioTransport<S> {
+        TokioTransport { stream }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Transport for TokioTransport<S> {
+    fn poll_read_buf(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut BytesMut,
+    ) -> Poll<io::Result<usize>> {
+        poll_read_buf(Pin::new(&mut self.stream), cx, buf)
+    }
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
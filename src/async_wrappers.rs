@@ -0,0 +1,496 @@
+//! The public wrappers of the async runtimes.
+//!
+//! The server streams, servers and clients of `async_tokio` and `async_smol`
+//! only differ in their transport and the networking types of the runtime,
+//! so they are generated by the macros in this module.
+
+/// Generates `PixelflutServerStream` and `PixelflutServer` for an async runtime.
+///
+/// The documentation of both types is given by the caller,
+/// the methods are documented here with examples for the given module.
+macro_rules! server_wrappers {
+    (
+        module: $module:literal,
+        net: $net:literal,
+        transport: $transport:ident,
+        io: $read:path, $write:path,
+        listener: $listener:ty,
+        tcp_stream: $tcp_stream:ty,
+        to_socket_addrs: $addrs:path,
+        spawn: $spawn:path,
+        $(#[$stream_attr:meta])*
+        pub struct PixelflutServerStream;
+        $(#[$server_attr:meta])*
+        pub struct PixelflutServer;
+    ) => {
+        $(#[$stream_attr])*
+        pub struct PixelflutServerStream<S = $tcp_stream> {
+            inner: $crate::connection::ServerConnection<$transport<S>>,
+        }
+
+        impl<S: $read + $write + Unpin> PixelflutServerStream<S> {
+            pub fn new(stream: S, dimensions: (u32, u32)) -> PixelflutServerStream<S> {
+                PixelflutServerStream::with_capacity(
+                    stream,
+                    dimensions,
+                    $crate::connection::SERVER_READ_BUFFER_DEFAULT_CAPACITY,
+                )
+            }
+
+            pub fn with_capacity(
+                stream: S,
+                dimensions: (u32, u32),
+                capacity: usize,
+            ) -> PixelflutServerStream<S> {
+                PixelflutServerStream {
+                    inner: $crate::connection::ServerConnection::new(
+                        $transport::new(stream, capacity),
+                        dimensions,
+                        capacity,
+                    ),
+                }
+            }
+
+            /// Creates a new `PixelflutServerStream` that answers `HELP` with a custom text.
+            ///
+            /// The text may contain multiple lines.
+            pub fn with_help(
+                stream: S,
+                dimensions: (u32, u32),
+                help: impl Into<::std::borrow::Cow<'static, str>>,
+            ) -> PixelflutServerStream<S> {
+                let mut server = PixelflutServerStream::new(stream, dimensions);
+                server.inner.set_help(help.into());
+                server
+            }
+
+            /// Returns a reference to the underlying stream.
+            pub fn get_ref(&self) -> &S {
+                &self.inner.transport.stream
+            }
+
+            /// Returns a mutable reference to the underlying stream.
+            pub fn get_mut(&mut self) -> &mut S {
+                &mut self.inner.transport.stream
+            }
+
+            /// Returns the offset that is currently applied to received pixels.
+            ///
+            /// The offset is set by the client with an `OFFSET x y` command.
+            pub fn offset(&self) -> $crate::Coordinate {
+                self.inner.offset()
+            }
+
+            /// Sets the function used to answer `PX x y` requests.
+            ///
+            /// The function returns the current color at the coordinate
+            /// or `None` if the coordinate is outside of the canvas.
+            /// Without a lookup, `PX x y` requests are answered with an `ERROR`.
+            pub fn set_pixel_lookup(
+                &mut self,
+                lookup: impl Fn($crate::Coordinate) -> Option<$crate::Color> + Send + Sync + 'static,
+            ) {
+                self.inner.set_pixel_lookup(lookup);
+            }
+
+            /// Sets the policy deciding when invalid lines close the connection.
+            ///
+            /// By default, the first invalid line is returned as an error.
+            pub fn set_error_policy(&mut self, error_policy: $crate::ErrorPolicy) {
+                self.inner.set_error_policy(error_policy);
+            }
+
+            /// Returns the number of invalid lines received so far.
+            pub fn error_count(&self) -> u64 {
+                self.inner.error_count()
+            }
+
+            /// Registers a handler answering [`Command::Custom`] commands with the given verb.
+            ///
+            /// The handler gets the arguments of the command and pushes its answers to the `Vec`.
+            /// Errors are answered with an `ERROR` and counted by the error policy,
+            /// like custom commands without a handler.
+            /// The built-in commands can not be replaced.
+            ///
+            /// Registering a command raises the line limit to at least 1024 bytes,
+            /// see [`set_max_line_length`](Self::set_max_line_length).
+            ///
+            /// # Examples
+            ///
+            /// ```no_run
+            #[doc = concat!("use pixelflut::", $module, "::PixelflutServerStream;")]
+            /// use pixelflut::Response;
+            #[doc = concat!("use ", $net, "::TcpStream;")]
+            ///
+            /// async fn handle_client(stream: TcpStream) -> pixelflut::PixelflutResult<()> {
+            ///     let mut stream = PixelflutServerStream::new(stream, (800, 600));
+            ///     stream.register_command("PING", |_args, responses| {
+            ///         responses.push(Response::Custom("PONG".into()));
+            ///         Ok(())
+            ///     });
+            ///     while let Some(_pixel) = stream.read_pixel().await? {}
+            ///     Ok(())
+            /// }
+            /// ```
+            ///
+            /// [`Command::Custom`]: crate::Command::Custom
+            pub fn register_command(
+                &mut self,
+                verb: impl Into<String>,
+                handler: impl FnMut(&[String], &mut Vec<$crate::Response>) -> $crate::PixelflutResult<()>
+                    + Send
+                    + 'static,
+            ) {
+                self.inner.register_command(verb.into(), Box::new(handler));
+            }
+
+            /// Sets the maximum length of a line, including the newline.
+            ///
+            /// The default fits the longest `PX` command,
+            /// registered custom commands and `run_handler` raise it to 1024 bytes.
+            /// Custom commands with longer arguments need a larger limit.
+            /// Longer lines end the connection with an error.
+            pub fn set_max_line_length(&mut self, max_line_length: usize) {
+                self.inner.set_max_line_length(max_line_length);
+            }
+
+            /// Reads the next pixel.
+            ///
+            /// Other commands like `SIZE` are answered along the way.
+            /// Returns `None` at the end of the stream.
+            /// The stream can also be consumed as a [`Stream`](futures_core::Stream) of pixels.
+            pub async fn read_pixel(&mut self) -> $crate::PixelflutResult<Option<$crate::Pixel>> {
+                self.inner.read_pixel().await
+            }
+
+            /// Reads all pixels that are currently buffered and passes them to a callback.
+            ///
+            /// If no complete command is buffered, the stream is read once.
+            /// Other commands like `SIZE` are answered along the way.
+            ///
+            /// # Returns
+            /// The number of pixels passed to the callback, which might be zero,
+            /// if only other commands were received,
+            /// or `None` at the end of the stream.
+            pub async fn read_pixels_with(
+                &mut self,
+                f: impl FnMut($crate::Pixel),
+            ) -> $crate::PixelflutResult<Option<usize>> {
+                self.inner.read_pixels_with(f).await
+            }
+
+            /// Reads all pixels that are currently buffered and appends them to `pixels`.
+            ///
+            /// See [`read_pixels_with`](Self::read_pixels_with).
+            ///
+            /// # Examples
+            ///
+            /// ```no_run
+            #[doc = concat!("use pixelflut::", $module, "::PixelflutServerStream;")]
+            #[doc = concat!("use ", $net, "::TcpStream;")]
+            ///
+            /// async fn handle_client(stream: TcpStream) -> pixelflut::PixelflutResult<()> {
+            ///     let mut stream = PixelflutServerStream::new(stream, (800, 600));
+            ///     let mut pixels = Vec::new();
+            ///     while let Some(_count) = stream.read_pixels(&mut pixels).await? {
+            ///         for pixel in pixels.drain(..) {
+            ///             println!("{}", pixel);
+            ///         }
+            ///     }
+            ///     Ok(())
+            /// }
+            /// ```
+            pub async fn read_pixels(
+                &mut self,
+                pixels: &mut Vec<$crate::Pixel>,
+            ) -> $crate::PixelflutResult<Option<usize>> {
+                self.read_pixels_with(|pixel| pixels.push(pixel)).await
+            }
+
+            /// Passes all received commands to a [`PixelflutHandler`] until the end of the stream.
+            ///
+            /// Commands the handler does not answer itself are handled like in
+            /// [`read_pixel`](Self::read_pixel) and pixels are passed to
+            /// [`on_pixel`](crate::PixelflutHandler::on_pixel).
+            /// An error is returned after
+            /// [`on_disconnect`](crate::PixelflutHandler::on_disconnect) was called.
+            ///
+            /// The handler may answer custom commands in
+            /// [`on_unknown_command`](crate::PixelflutHandler::on_unknown_command),
+            /// so the line limit is raised to at least 1024 bytes, like by `register_command`.
+            ///
+            /// [`PixelflutHandler`]: crate::PixelflutHandler
+            pub async fn run_handler(
+                &mut self,
+                handler: &mut impl $crate::PixelflutHandler,
+            ) -> $crate::PixelflutResult<()> {
+                self.inner.run_handler(handler).await
+            }
+        }
+
+        /// Pixels received from the client.
+        ///
+        /// Other commands like `SIZE` are answered while polling.
+        /// The stream ends at the end of the connection.
+        /// An error ends the connection, so the stream should not be polled afterwards.
+        impl<S: $read + $write + Unpin> ::futures_core::Stream for PixelflutServerStream<S> {
+            type Item = $crate::PixelflutResult<$crate::Pixel>;
+
+            fn poll_next(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+            ) -> ::std::task::Poll<Option<Self::Item>> {
+                self.get_mut()
+                    .inner
+                    .poll_read_pixel(cx)
+                    .map(Result::transpose)
+            }
+        }
+
+        $(#[$server_attr])*
+        pub struct PixelflutServer {
+            listener: $listener,
+            canvas: ::std::sync::Arc<$crate::Canvas>,
+            error_policy: $crate::ErrorPolicy,
+        }
+
+        impl PixelflutServer {
+            /// Binds a new server to the given address.
+            pub async fn bind(
+                addr: impl $addrs,
+                canvas: ::std::sync::Arc<$crate::Canvas>,
+            ) -> $crate::PixelflutResult<PixelflutServer> {
+                Ok(PixelflutServer {
+                    listener: <$listener>::bind(addr).await?,
+                    canvas,
+                    error_policy: $crate::ErrorPolicy::default(),
+                })
+            }
+
+            /// Returns the address the server is listening on.
+            pub fn local_addr(&self) -> $crate::PixelflutResult<::std::net::SocketAddr> {
+                Ok(self.listener.local_addr()?)
+            }
+
+            /// Returns the canvas the server draws on.
+            pub fn canvas(&self) -> &::std::sync::Arc<$crate::Canvas> {
+                &self.canvas
+            }
+
+            /// Sets the error policy used for every new connection.
+            ///
+            /// See [`PixelflutServerStream::set_error_policy`].
+            pub fn set_error_policy(&mut self, error_policy: $crate::ErrorPolicy) {
+                self.error_policy = error_policy;
+            }
+
+            /// Accepts connections until an error occurs while accepting.
+            ///
+            /// Errors in a single connection only close that connection.
+            pub async fn run(self) -> $crate::PixelflutResult<()> {
+                loop {
+                    let (stream, _addr) = self.listener.accept().await?;
+                    let canvas = self.canvas.clone();
+                    let error_policy = self.error_policy;
+                    $spawn(async move {
+                        let _ = handle_connection(stream, canvas, error_policy).await;
+                    });
+                }
+            }
+        }
+
+        async fn handle_connection(
+            stream: $tcp_stream,
+            canvas: ::std::sync::Arc<$crate::Canvas>,
+            error_policy: $crate::ErrorPolicy,
+        ) -> $crate::PixelflutResult<()> {
+            let mut stream = PixelflutServerStream::new(stream, canvas.dimensions());
+            stream.set_error_policy(error_policy);
+            let lookup_canvas = canvas.clone();
+            stream.set_pixel_lookup(move |position| lookup_canvas.get(position));
+            let set_pixel = |pixel: $crate::Pixel| canvas.set(&pixel);
+            while let Some(_count) = stream.read_pixels_with(set_pixel).await? {}
+            Ok(())
+        }
+    };
+}
+
+/// Generates `PixelflutClient` for an async runtime.
+///
+/// The documentation of the type is given by the caller.
+macro_rules! client_wrapper {
+    (
+        transport: $transport:ident,
+        io: $read:path, $write:path,
+        tcp_stream: $tcp_stream:ty,
+        to_socket_addrs: $addrs:path,
+        $(#[$attr:meta])*
+        pub struct PixelflutClient;
+    ) => {
+        $(#[$attr])*
+        pub struct PixelflutClient<S = $tcp_stream> {
+            inner: $crate::connection::ClientConnection<$transport<S>>,
+        }
+
+        impl PixelflutClient<$tcp_stream> {
+            /// Connect to a Pixelflut server.
+            pub async fn connect(addr: impl $addrs) -> $crate::PixelflutResult<PixelflutClient> {
+                let stream = <$tcp_stream>::connect(addr).await?;
+                Ok(PixelflutClient::new(stream))
+            }
+        }
+
+        impl<S: $read + $write + Unpin> PixelflutClient<S> {
+            /// Creates a client on an already connected stream.
+            pub fn new(stream: S) -> PixelflutClient<S> {
+                PixelflutClient {
+                    inner: $crate::connection::ClientConnection::new($transport::new(
+                        stream,
+                        $crate::connection::CLIENT_READ_BUFFER_CAPACITY,
+                    )),
+                }
+            }
+
+            /// Returns a reference to the underlying stream.
+            pub fn get_ref(&self) -> &S {
+                &self.inner.transport.stream
+            }
+
+            /// Returns a mutable reference to the underlying stream.
+            pub fn get_mut(&mut self) -> &mut S {
+                &mut self.inner.transport.stream
+            }
+
+            /// Sets the encoding used for all following pixels.
+            ///
+            /// The server must support the binary protocol if
+            /// [`PixelEncoding::Binary`](crate::PixelEncoding::Binary) is used.
+            pub fn set_encoding(&mut self, encoding: $crate::PixelEncoding) {
+                self.inner.set_encoding(encoding);
+            }
+
+            /// Writes a Pixel to the server.
+            ///
+            /// A buffered stream is used for sending.
+            /// The pixel is only send if the buffer is full or [flush] is called.
+            ///
+            /// [flush]: Self::flush
+            pub async fn set(
+                &mut self,
+                x: u32,
+                y: u32,
+                color: impl Into<$crate::Color>,
+            ) -> $crate::PixelflutResult<()> {
+                self.inner
+                    .set($crate::Pixel::new((x, y).into(), color.into()))
+                    .await
+            }
+
+            /// Writes multiple pixels to the server, e.g. a shape drawn by a [`Painter`].
+            ///
+            /// The pixels are buffered like with [`set`](Self::set).
+            ///
+            /// [`Painter`]: crate::Painter
+            pub async fn set_pixels(
+                &mut self,
+                pixels: impl IntoIterator<Item = $crate::Pixel>,
+            ) -> $crate::PixelflutResult<()> {
+                for pixel in pixels {
+                    self.inner.set(pixel).await?;
+                }
+                Ok(())
+            }
+
+            /// Asks the server for the dimensions of the canvas.
+            ///
+            /// A `SIZE` command is send to the server.
+            /// If the server replies with a `SIZE <width> <height>` packet,
+            /// the dimensions will be returned.
+            ///
+            /// # Returns
+            /// Ok((width, height)) on success
+            pub async fn dimensions(&mut self) -> $crate::PixelflutResult<(u32, u32)> {
+                self.inner.dimensions().await
+            }
+
+            /// Reads the color of a pixel from the server.
+            ///
+            /// A `PX <x> <y>` command is send to the server.
+            /// If the server replies with a `PX <x> <y> <color>` packet,
+            /// the color will be returned.
+            pub async fn get(&mut self, x: u32, y: u32) -> $crate::PixelflutResult<$crate::Color> {
+                self.inner.get(x, y).await
+            }
+
+            /// Asks the server for its help text.
+            ///
+            /// A `HELP` command is send to the server.
+            /// The `HELP <line>` lines of the reply are returned without the prefix.
+            pub async fn help(&mut self) -> $crate::PixelflutResult<String> {
+                self.inner.help().await
+            }
+
+            /// Sets the offset the server adds to all following pixels.
+            ///
+            /// Buffered pixels are flushed before the `OFFSET <x> <y>` command is send,
+            /// so they are still drawn with the previous offset.
+            pub async fn set_offset(&mut self, x: u32, y: u32) -> $crate::PixelflutResult<()> {
+                self.inner.set_offset(x, y).await
+            }
+
+            /// Writes a pre-formatted `PixelBuffer` to the server.
+            ///
+            /// Pixels that were set before are send first.
+            pub async fn write_buffer(
+                &mut self,
+                buffer: &$crate::PixelBuffer,
+            ) -> $crate::PixelflutResult<()> {
+                self.inner.write_buffer(buffer).await
+            }
+
+            /// Flushes the internal buffer to the server.
+            pub async fn flush(&mut self) -> $crate::PixelflutResult<()> {
+                self.inner.flush().await
+            }
+        }
+
+        /// Sends pixels to the server.
+        ///
+        /// Pixels are buffered like with [`set`](PixelflutClient::set)
+        /// and written when the buffer is full or the sink is flushed.
+        impl<S: $read + $write + Unpin> ::futures_sink::Sink<$crate::Pixel> for PixelflutClient<S> {
+            type Error = $crate::PixelflutError;
+
+            fn poll_ready(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+            ) -> ::std::task::Poll<$crate::PixelflutResult<()>> {
+                self.get_mut().inner.poll_ready(cx)
+            }
+
+            fn start_send(
+                self: ::std::pin::Pin<&mut Self>,
+                pixel: $crate::Pixel,
+            ) -> $crate::PixelflutResult<()> {
+                self.get_mut().inner.start_send(pixel)
+            }
+
+            fn poll_flush(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+            ) -> ::std::task::Poll<$crate::PixelflutResult<()>> {
+                self.get_mut().inner.poll_flush(cx)
+            }
+
+            fn poll_close(
+                self: ::std::pin::Pin<&mut Self>,
+                cx: &mut ::std::task::Context<'_>,
+            ) -> ::std::task::Poll<$crate::PixelflutResult<()>> {
+                self.get_mut().inner.poll_close(cx)
+            }
+        }
+    };
+}
+
+pub(crate) use client_wrapper;
+pub(crate) use server_wrappers;
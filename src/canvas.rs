@@ -0,0 +1,179 @@
+//! A module containing a shared framebuffer for pixelflut servers.
+use crate::{Color, Coordinate, Pixel};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// How a [`Canvas`] handles the alpha channel of new pixels.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AlphaMode {
+    /// Stores the color including its alpha channel, replacing the existing pixel.
+    Replace,
+    /// Blends the color over the existing pixel, see [`Color::blend_over`].
+    #[default]
+    Blend,
+    /// Drops the alpha channel and stores the color as opaque.
+    Ignore,
+}
+
+/// Shared RGBA framebuffer.
+///
+/// Every pixel is stored in an atomic integer,
+/// so the canvas can be written by multiple connections at the same time without locking.
+/// Share it between threads or tasks with an [`Arc`](std::sync::Arc).
+///
+/// New pixels are alpha-blended over the existing pixels by default,
+/// use [`with_alpha_mode`](Self::with_alpha_mode) to select a different [`AlphaMode`].
+///
+/// # Examples
+///
+/// ```
+/// use pixelflut::{Canvas, Color, Pixel};
+/// let canvas = Canvas::new(800, 600);
+/// canvas.set(&Pixel::new((12, 34).into(), Color::rgb(255, 0, 10)));
+/// assert_eq!(canvas.get((12, 34).into()), Some(Color::rgb(255, 0, 10)));
+/// assert_eq!(canvas.get((800, 0).into()), None);
+///
+/// canvas.set(&Pixel::new((12, 34).into(), Color::rgba(0, 0, 255, 128)));
+/// assert_eq!(canvas.get((12, 34).into()), Some(Color::rgb(127, 0, 133)));
+/// ```
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Box<[AtomicU32]>,
+    alpha_mode: AlphaMode,
+}
+
+impl Canvas {
+    /// Constructs a new black `Canvas` with the given dimensions.
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas::with_alpha_mode(width, height, AlphaMode::default())
+    }
+
+    /// Constructs a new black `Canvas` with the given dimensions and [`AlphaMode`].
+    pub fn with_alpha_mode(width: u32, height: u32, alpha_mode: AlphaMode) -> Canvas {
+        let pixels = (0..width as usize * height as usize)
+            .map(|_| AtomicU32::new(pack(Color::rgb(0, 0, 0))))
+            .collect();
+        Canvas {
+            width,
+            height,
+            pixels,
+            alpha_mode,
+        }
+    }
+
+    /// Returns how the alpha channel of new pixels is handled.
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Returns the width of the canvas.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the canvas.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the dimensions of the canvas as `(width, height)`.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn index(&self, position: Coordinate) -> Option<usize> {
+        if position.x < self.width && position.y < self.height {
+            Some(position.y as usize * self.width as usize + position.x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the color at a position or `None` if the position is outside of the canvas.
+    pub fn get(&self, position: Coordinate) -> Option<Color> {
+        self.index(position)
+            .map(|index| unpack(self.pixels[index].load(Ordering::Relaxed)))
+    }
+
+    /// Sets a pixel on the canvas, according to the [`AlphaMode`] of the canvas.
+    ///
+    /// Pixels outside of the canvas are ignored.
+    pub fn set(&self, pixel: &Pixel) {
+        let index = match self.index(pixel.position) {
+            Some(index) => index,
+            None => return,
+        };
+        let color = pixel.color;
+        match self.alpha_mode {
+            AlphaMode::Blend if color.alpha() != 255 => {
+                let _ =
+                    self.pixels[index].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                        Some(pack(color.blend_over(unpack(old))))
+                    });
+            }
+            AlphaMode::Ignore => self.pixels[index].store(
+                pack(Color::rgb(color.r, color.g, color.b)),
+                Ordering::Relaxed,
+            ),
+            _ => self.pixels[index].store(pack(color), Ordering::Relaxed),
+        }
+    }
+
+    /// Copies the canvas into a row-major buffer with 4 bytes (red, green, blue, alpha) per pixel.
+    pub fn to_rgba_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| pixel.load(Ordering::Relaxed).to_be_bytes())
+            .collect()
+    }
+}
+
+fn pack(color: Color) -> u32 {
+    let (r, g, b, a) = color.normalized();
+    u32::from_be_bytes([r, g, b, a])
+}
+
+fn unpack(value: u32) -> Color {
+    let [r, g, b, a] = value.to_be_bytes();
+    Color::packed(r, g, b, a)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AlphaMode, Canvas, Color, Pixel};
+
+    #[test]
+    fn canvas_set_get() {
+        let canvas = Canvas::with_alpha_mode(2, 2, AlphaMode::Replace);
+        assert_eq!(canvas.dimensions(), (2, 2));
+        assert_eq!(canvas.get((1, 1).into()), Some(Color::rgb(0, 0, 0)));
+
+        canvas.set(&Pixel::new((1, 0).into(), Color::rgba(1, 2, 3, 4)));
+        canvas.set(&Pixel::new((2, 0).into(), Color::rgb(5, 6, 7)));
+        assert_eq!(canvas.get((1, 0).into()), Some(Color::rgba(1, 2, 3, 4)));
+        assert_eq!(canvas.get((0, 2).into()), None);
+        assert_eq!(
+            canvas.to_rgba_bytes(),
+            vec![0, 0, 0, 255, 1, 2, 3, 4, 0, 0, 0, 255, 0, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn canvas_alpha_modes() {
+        let pixel = Pixel::new((0, 0).into(), Color::rgba(255, 0, 0, 0x40));
+
+        let canvas = Canvas::new(1, 1);
+        canvas.set(&pixel);
+        assert_eq!(canvas.get((0, 0).into()), Some(Color::rgb(0x40, 0, 0)));
+
+        let canvas = Canvas::with_alpha_mode(1, 1, AlphaMode::Replace);
+        canvas.set(&pixel);
+        assert_eq!(canvas.get((0, 0).into()), Some(pixel.color));
+
+        let canvas = Canvas::with_alpha_mode(1, 1, AlphaMode::Ignore);
+        canvas.set(&pixel);
+        assert_eq!(canvas.get((0, 0).into()), Some(Color::rgb(255, 0, 0)));
+    }
+}
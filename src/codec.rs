@@ -0,0 +1,216 @@
+//! Framing of Pixelflut streams, shared by all async runtimes.
+use bytes::{BufMut, BytesMut};
+use std::fmt::Write;
+
+use crate::command::{Command, Response};
+use crate::error::{PixelflutErrorKind, PixelflutResult};
+use crate::pixel::MAX_FORMATTED_PIXEL_SIZE_NEWLINE;
+use crate::pixel_buffer::{decode_binary_pixel, BINARY_PIXEL_SIZE};
+use crate::{PixelBuffer, PixelEncoding};
+
+/// Codec for the server side of a connection.
+///
+/// Decodes [`Command`]s, including binary pixels, and encodes [`Response`]s.
+/// Invalid lines are removed from the buffer before the error is returned,
+/// so decoding can continue with the next line.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::BytesMut;
+/// use pixelflut::async_tokio::PixelflutServerCodec;
+/// use pixelflut::{Command, Pixel};
+/// use tokio_util::codec::Decoder;
+///
+/// let mut codec = PixelflutServerCodec::new();
+/// let mut buf = BytesMut::from(&b"PX 1 2 ff0000\nSI"[..]);
+/// assert_eq!(
+///     codec.decode(&mut buf).unwrap(),
+///     Some(Command::Px(Pixel::new((1, 2).into(), (255, 0, 0).into())))
+/// );
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PixelflutServerCodec {
+    max_line_length: usize,
+}
+
+#[cfg_attr(not(feature = "tokio-rt"), allow(dead_code))]
+impl PixelflutServerCodec {
+    /// Creates a codec with a line limit fitting the longest `PX` command.
+    pub fn new() -> PixelflutServerCodec {
+        PixelflutServerCodec {
+            max_line_length: MAX_FORMATTED_PIXEL_SIZE_NEWLINE,
+        }
+    }
+
+    /// Returns the maximum length of a line, including the newline.
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    /// Sets the maximum length of a line, including the newline.
+    ///
+    /// Longer lines are decoded as an `Io` error.
+    pub fn set_max_line_length(&mut self, max_line_length: usize) {
+        self.max_line_length = max_line_length;
+    }
+
+    /// Decodes the next complete command from the buffer.
+    pub(crate) fn decode_command(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> PixelflutResult<Option<Command>> {
+        if src.starts_with(b"PB") {
+            // binary pixels may contain newlines, so they are framed by length
+            if src.len() >= BINARY_PIXEL_SIZE {
+                let mut bytes = [0u8; BINARY_PIXEL_SIZE];
+                bytes.copy_from_slice(&src[..BINARY_PIXEL_SIZE]);
+                let _ = src.split_to(BINARY_PIXEL_SIZE);
+                return Ok(Some(Command::Px(decode_binary_pixel(&bytes))));
+            }
+        } else if let Some(pos) = memchr::memchr(b'\n', src.as_ref()) {
+            // complete lines are limited as well, so the result does not depend on the framing
+            let command = if pos + 1 > self.max_line_length {
+                Err(PixelflutErrorKind::Io.with_description("line is to long"))
+            } else {
+                Command::from_bytes_or_custom(&src[0..pos])
+            };
+            let _ = src.split_to(pos + 1);
+            return command.map(Some);
+        } else if src.len() > self.max_line_length {
+            return Err(PixelflutErrorKind::Io.with_description("line is to long"));
+        }
+        Ok(None)
+    }
+
+    /// Decodes the next command at the end of the stream.
+    ///
+    /// Remaining bytes, that are not a complete command, are an error.
+    pub(crate) fn decode_command_eof(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> PixelflutResult<Option<Command>> {
+        match self.decode_command(src)? {
+            None if !src.is_empty() => {
+                Err(PixelflutErrorKind::Io.with_description("Unexpected end of stream"))
+            }
+            command => Ok(command),
+        }
+    }
+
+    /// Appends a response to the buffer.
+    pub(crate) fn encode_response(
+        &mut self,
+        response: &Response,
+        dst: &mut BytesMut,
+    ) -> PixelflutResult<()> {
+        writeln!(dst, "{}", response)
+            .map_err(|_| PixelflutErrorKind::Io.with_description("formatting failed"))
+    }
+}
+
+impl Default for PixelflutServerCodec {
+    fn default() -> PixelflutServerCodec {
+        PixelflutServerCodec::new()
+    }
+}
+
+/// Codec for the client side of a connection.
+///
+/// Encodes [`Command`]s and decodes [`Response`]s.
+/// A [`Response::Help`] is only decoded after its terminating `HELP` line was received.
+///
+/// # Examples
+///
+/// ```
+/// use bytes::BytesMut;
+/// use pixelflut::async_tokio::PixelflutClientCodec;
+/// use pixelflut::{Command, Response};
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = PixelflutClientCodec::new();
+/// let mut buf = BytesMut::new();
+/// codec.encode(Command::Size, &mut buf).unwrap();
+/// assert_eq!(&buf[..], b"SIZE\n");
+///
+/// let mut buf = BytesMut::from(&b"HELP first\nHELP second\nHELP\n"[..]);
+/// assert_eq!(
+///     codec.decode(&mut buf).unwrap(),
+///     Some(Response::Help("first\nsecond".into()))
+/// );
+/// ```
+pub struct PixelflutClientCodec {
+    pixel_buf: PixelBuffer,
+}
+
+#[cfg_attr(not(feature = "tokio-rt"), allow(dead_code))]
+impl PixelflutClientCodec {
+    /// Creates a codec using the text encoding for pixels.
+    pub fn new() -> PixelflutClientCodec {
+        PixelflutClientCodec {
+            pixel_buf: PixelBuffer::with_capacity(MAX_FORMATTED_PIXEL_SIZE_NEWLINE),
+        }
+    }
+
+    /// Returns the encoding used for pixels.
+    pub fn encoding(&self) -> PixelEncoding {
+        self.pixel_buf.encoding()
+    }
+
+    /// Sets the encoding used for pixels.
+    ///
+    /// The server must support the binary protocol if
+    /// [`PixelEncoding::Binary`] is used.
+    pub fn set_encoding(&mut self, encoding: PixelEncoding) {
+        self.pixel_buf.set_encoding(encoding);
+    }
+
+    /// Decodes the next complete response from the buffer.
+    pub(crate) fn decode_response(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> PixelflutResult<Option<Response>> {
+        // a help response spans multiple lines, all other responses are a single line
+        let mut end = 0;
+        loop {
+            let pos = match memchr::memchr(b'\n', &src[end..]) {
+                Some(pos) => end + pos,
+                None => return Ok(None),
+            };
+            let line = src[end..pos].strip_suffix(b"\r").unwrap_or(&src[end..pos]);
+            let is_help_line = line.starts_with(b"HELP ");
+            end = pos + 1;
+            if !is_help_line {
+                break;
+            }
+        }
+        let lines = src.split_to(end);
+        let lines = std::str::from_utf8(&lines)
+            .map_err(|_| PixelflutErrorKind::Parse.with_description("response is not UTF-8"))?;
+        lines.trim_end_matches(&['\r', '\n'][..]).parse().map(Some)
+    }
+
+    /// Appends a command to the buffer, using the configured encoding for pixels.
+    pub(crate) fn encode_command(
+        &mut self,
+        command: &Command,
+        dst: &mut BytesMut,
+    ) -> PixelflutResult<()> {
+        if let Command::Px(pixel) = command {
+            self.pixel_buf.write_pixel(pixel);
+            dst.put_slice(self.pixel_buf.as_slice());
+            self.pixel_buf.clear();
+            Ok(())
+        } else {
+            writeln!(dst, "{}", command)
+                .map_err(|_| PixelflutErrorKind::Io.with_description("formatting failed"))
+        }
+    }
+}
+
+impl Default for PixelflutClientCodec {
+    fn default() -> PixelflutClientCodec {
+        PixelflutClientCodec::new()
+    }
+}
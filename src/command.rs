@@ -1,26 +1,65 @@
 //! A module for working with Pixelflut commands.
 
-use std::fmt;
-use std::str::FromStr;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
 
 use crate::error::{PixelflutError, PixelflutErrorKind, PixelflutResult};
+use crate::parser::{parse_color, parse_decimal, Arguments};
 use crate::pixel::{Color, Coordinate, Pixel};
-use std::borrow::Cow;
 
 /// A pixelflut command
 ///
 /// Send to the Server
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
     Px(Pixel),
+    /// Asks the server for the color of a pixel (`PX x y`).
+    GetPx(Coordinate),
+    /// Translates all following pixels of the connection (`OFFSET x y`).
+    Offset(Coordinate),
     Size,
+    Help,
+    /// A command that is not part of the protocol, e.g. `TEXT` or `STATS`.
+    ///
+    /// Servers can answer them with handlers registered on the server stream.
+    /// The payload is boxed to keep the built-in commands small.
+    Custom(Box<CustomCommand>),
+}
+
+/// A command that is not part of the protocol, see [`Command::Custom`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomCommand {
+    pub verb: String,
+    pub args: Vec<String>,
+}
+
+impl CustomCommand {
+    pub fn new(verb: String, args: Vec<String>) -> CustomCommand {
+        CustomCommand { verb, args }
+    }
 }
 
 impl fmt::Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Command::Px(ref pixel) => write!(f, "PX {}", pixel),
+            Command::GetPx(ref coordinate) => write!(f, "PX {}", coordinate),
+            Command::Offset(ref coordinate) => write!(f, "OFFSET {}", coordinate),
             Command::Size => write!(f, "SIZE"),
+            Command::Help => write!(f, "HELP"),
+            Command::Custom(ref custom) => {
+                write!(f, "{}", custom.verb)?;
+                for arg in &custom.args {
+                    write!(f, " {}", arg)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -28,41 +67,94 @@ impl fmt::Display for Command {
 impl FromStr for Command {
     type Err = PixelflutError;
 
+    /// Parses a command, see [`Command::from_bytes`].
     fn from_str(s: &str) -> PixelflutResult<Command> {
-        let mut iter = s.split_whitespace();
+        Command::from_bytes(s.as_bytes())
+    }
+}
 
-        let command = iter.next().ok_or(PixelflutErrorKind::InvalidCommand)?;
+impl Command {
+    /// Parses a command directly from bytes.
+    ///
+    /// The [`FromStr`] implementation uses the same parser.
+    /// It skips the UTF-8 validation and does not allocate.
+    /// Unknown verbs are an `InvalidCommand` error,
+    /// only the server streams turn them into [`Command::Custom`].
+    /// Arguments are separated by ASCII whitespace, so a trailing newline is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Command, Pixel};
+    /// assert_eq!(
+    ///     Command::from_bytes(b"PX 12 34 ff000a\n").unwrap(),
+    ///     Command::Px(Pixel::new((12, 34).into(), (255, 0, 10).into()))
+    /// );
+    /// assert_eq!(Command::from_bytes(b"SIZE").unwrap(), Command::Size);
+    /// assert!(Command::from_bytes(b"PX 12").is_err());
+    /// ```
+    pub fn from_bytes(line: &[u8]) -> PixelflutResult<Command> {
+        let mut args = Arguments::new(line);
+
+        let command = args.next().ok_or(PixelflutErrorKind::InvalidCommand)?;
 
         let command = match command {
-            "PX" => Command::Px(Pixel::new(
-                Coordinate::new(
-                    iter.next()
-                        .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                        .parse()?,
-                    iter.next()
-                        .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                        .parse()?,
-                ),
-                iter.next()
-                    .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                    .parse::<Color>()?,
-            )),
-            "SIZE" => {
-                if iter.next().is_some() {
-                    return Err(PixelflutErrorKind::WrongNumberOfArguments.into());
-                } else {
-                    Command::Size
+            b"PX" => {
+                let position = Coordinate::new(
+                    parse_decimal(args.next_required()?)?,
+                    parse_decimal(args.next_required()?)?,
+                );
+                match args.next() {
+                    Some(color) => Command::Px(Pixel::new(position, parse_color(color)?)),
+                    None => Command::GetPx(position),
                 }
             }
+            b"OFFSET" => Command::Offset(Coordinate::new(
+                parse_decimal(args.next_required()?)?,
+                parse_decimal(args.next_required()?)?,
+            )),
+            b"SIZE" => Command::Size,
+            b"HELP" => Command::Help,
             _ => return Err(PixelflutErrorKind::InvalidCommand.into()),
         };
 
-        if iter.next() == None {
+        if args.next().is_none() {
             Ok(command)
         } else {
             Err(PixelflutErrorKind::WrongNumberOfArguments.into())
         }
     }
+
+    /// Parses a command like [`Command::from_bytes`], but keeps unknown verbs as [`Command::Custom`].
+    ///
+    /// Used by the server streams, which answer custom commands with their registry.
+    #[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+    pub(crate) fn from_bytes_or_custom(line: &[u8]) -> PixelflutResult<Command> {
+        Command::from_bytes(line).or_else(|err| match err.kind() {
+            PixelflutErrorKind::InvalidCommand => custom_from_bytes(line, err),
+            _ => Err(err),
+        })
+    }
+}
+
+/// Builds a [`Command::Custom`] from a line with an unknown verb.
+///
+/// Kept out of line, so the built-in commands are parsed without the UTF-8 handling.
+/// An empty line fails with the original error.
+#[cold]
+fn custom_from_bytes(line: &[u8], err: PixelflutError) -> PixelflutResult<Command> {
+    let to_string = |bytes: &[u8]| {
+        core::str::from_utf8(bytes)
+            .map(String::from)
+            .map_err(|_| PixelflutErrorKind::Parse.with_description("command is not UTF-8"))
+    };
+    let mut args = Arguments::new(line);
+    let verb = match args.next() {
+        Some(verb) => to_string(verb)?,
+        None => return Err(err),
+    };
+    let args = args.map(to_string).collect::<PixelflutResult<_>>()?;
+    Ok(Command::Custom(Box::new(CustomCommand::new(verb, args))))
 }
 
 impl From<Pixel> for Command {
@@ -71,14 +163,37 @@ impl From<Pixel> for Command {
     }
 }
 
+/// Help text send by the server streams, if no custom text is configured.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub static DEFAULT_HELP_TEXT: &str = "\
+rust-pixelflut server
+HELP: show this help
+SIZE: returns the size of the canvas as SIZE <width> <height>
+PX <x> <y>: returns the color of a pixel as PX <x> <y> <rrggbb>
+PX <x> <y> <rrggbb(aa)>: sets the color of a pixel
+OFFSET <x> <y>: adds an offset to all following pixels";
+
 /// A pixelflut command
 ///
 /// Send to the Client
+///
+/// A [`Response::Help`] spans multiple lines. Every line is prefixed with `HELP `
+/// and the response is terminated by a line only containing `HELP`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum Response {
-    Size { w: u32, h: u32 },
+    Size {
+        w: u32,
+        h: u32,
+    },
+    Px(Pixel),
+    Help(Cow<'static, str>),
     Error(Cow<'static, str>),
+    /// A line answering a [`Command::Custom`], send as it is.
+    ///
+    /// Custom lines are not parsed by the clients.
+    Custom(Cow<'static, str>),
 }
 
 impl fmt::Display for Response {
@@ -86,7 +201,15 @@ impl fmt::Display for Response {
         use Response::*;
         match self {
             Size { w, h } => write!(f, "SIZE {} {}", w, h),
+            Px(pixel) => write!(f, "PX {}", pixel),
+            Help(text) => {
+                for line in text.lines() {
+                    writeln!(f, "HELP {}", line)?;
+                }
+                write!(f, "HELP")
+            }
             Error(msg) => write!(f, "ERROR {}", msg),
+            Custom(line) => write!(f, "{}", line),
         }
     }
 }
@@ -95,27 +218,27 @@ impl FromStr for Response {
     type Err = PixelflutError;
 
     fn from_str(s: &str) -> PixelflutResult<Response> {
-        let mut iter = s.split_whitespace();
+        let mut args = Arguments::new(s.as_bytes());
 
-        let command = iter.next().ok_or(PixelflutErrorKind::InvalidCommand)?;
+        let command = args.next().ok_or(PixelflutErrorKind::InvalidCommand)?;
 
         let command = match command {
-            "SIZE" => {
-                if let Some(w) = iter.next() {
-                    Response::Size {
-                        w: w.parse()?,
-                        h: iter
-                            .next()
-                            .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                            .parse()?,
-                    }
-                } else {
-                    return Err(PixelflutErrorKind::WrongNumberOfArguments.into());
-                }
-            }
-            "ERROR" => {
+            b"SIZE" => Response::Size {
+                w: parse_decimal(args.next_required()?)?,
+                h: parse_decimal(args.next_required()?)?,
+            },
+            b"PX" => Response::Px(Pixel::new(
+                Coordinate::new(
+                    parse_decimal(args.next_required()?)?,
+                    parse_decimal(args.next_required()?)?,
+                ),
+                parse_color(args.next_required()?)?,
+            )),
+            b"HELP" => return parse_help(s),
+            b"ERROR" => {
+                // the message may contain whitespace, so it is not split into arguments
                 if s.len() > 6 {
-                    Response::Error(Cow::Owned(s[6..].into()))
+                    return Ok(Response::Error(Cow::Owned(s[6..].into())));
                 } else {
                     return Err(PixelflutErrorKind::WrongNumberOfArguments.into());
                 }
@@ -123,7 +246,7 @@ impl FromStr for Response {
             _ => return Err(PixelflutErrorKind::InvalidCommand.into()),
         };
 
-        if iter.next() == None {
+        if args.next().is_none() {
             Ok(command)
         } else {
             Err(PixelflutErrorKind::WrongNumberOfArguments.into())
@@ -131,10 +254,69 @@ impl FromStr for Response {
     }
 }
 
+/// Parses the lines of a multi-line [`Response::Help`].
+fn parse_help(s: &str) -> PixelflutResult<Response> {
+    let mut lines = s.lines();
+    let mut text = Vec::new();
+    loop {
+        match lines.next() {
+            Some("HELP") => break,
+            Some(line) => text.push(
+                line.strip_prefix("HELP ")
+                    .ok_or(PixelflutErrorKind::InvalidCommand)?,
+            ),
+            None => {
+                return Err(PixelflutErrorKind::Parse.with_description("help is not terminated"))
+            }
+        }
+    }
+    if lines.next().is_some() {
+        return Err(PixelflutErrorKind::WrongNumberOfArguments.into());
+    }
+    Ok(Response::Help(Cow::Owned(text.join("\n"))))
+}
+
+/// Callback used by the server streams to answer a [`Command::GetPx`].
+///
+/// Returns `None` if the coordinate is outside of the canvas.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) type PixelLookup = Box<dyn Fn(Coordinate) -> Option<Color> + Send + Sync>;
+
+/// Line limit of the server streams once a custom command is registered.
+///
+/// The default limit only fits the longest `PX` command.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) const CUSTOM_COMMAND_MAX_LINE_LENGTH: usize = 1024;
+
+/// Handler for a [`Command::Custom`] registered on a server stream.
+///
+/// Gets the arguments of the command and pushes the answers to the `Vec`.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) type CustomCommandHandler =
+    Box<dyn FnMut(&[String], &mut Vec<Response>) -> PixelflutResult<()> + Send>;
+
+/// Builds the answer to a [`Command::GetPx`] using an optional lookup.
+///
+/// The lookup is done at the translated position,
+/// but the answer contains the position as requested by the client.
+/// Positions that overflow when translated are out of range.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) fn get_px_response(
+    lookup: Option<&PixelLookup>,
+    position: Coordinate,
+    offset: Coordinate,
+) -> Response {
+    match lookup.map(|lookup| lookup(position.checked_add(offset)?)) {
+        Some(Some(color)) => Response::Px(Pixel::new(position, color)),
+        Some(None) => Response::Error("coordinate out of range".into()),
+        None => Response::Error("reading pixels is not supported".into()),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::command::{Command, Response};
-    use crate::Pixel;
+    use crate::command::{Command, CustomCommand, Response};
+    use crate::{Pixel, PixelflutErrorKind};
 
     #[test]
     fn display() {
@@ -150,7 +332,128 @@ mod test {
             "SIZE 12 34".parse().unwrap()
         );
         assert!("SIZE Blah".parse::<Response>().is_err());
+        assert!("SIZE +12 34".parse::<Response>().is_err());
+        assert!("PX +45 67 112255".parse::<Response>().is_err());
+        assert!("PX 1 2 3 4".parse::<Command>().is_err());
+        assert!("PX 1".parse::<Command>().is_err());
         assert!("FOO".parse::<Response>().is_err());
         assert!("FOO".parse::<Response>().is_err());
+        assert_eq!(
+            Response::Error("invalid command".into()),
+            "ERROR invalid command".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn from_bytes_matches_from_str() {
+        for line in [
+            "PX 45 67 112255",
+            "PX 45 67 112255aa",
+            "  PX\t45 67   112255 ",
+            "PX 45 67",
+            "OFFSET 1 2",
+            "SIZE",
+            "HELP",
+            "",
+            "PX",
+            "PX 45 67 112255 1",
+            "PX 45 67 1122",
+            "PX -1 67 112255",
+            "PX +1 67 112255",
+            "PX 1 67 +12255",
+            "PX\u{a0}1 67 112255",
+            "OFFSET +1 2",
+            "SIZE 1",
+            "HELP 1",
+            "OFFSET 1",
+            "FOO",
+            "TEXT 1 2 hello",
+        ] {
+            let from_str = line.parse::<Command>();
+            let from_bytes = Command::from_bytes(line.as_bytes());
+            match (from_str, from_bytes) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b, "{:?}", line),
+                (Err(a), Err(b)) => assert_eq!(a.kind(), b.kind(), "{:?}", line),
+                (a, b) => panic!("{:?}: {:?} != {:?}", line, a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn get_px() {
+        let command = Command::GetPx((45, 67).into());
+        assert_eq!(format!("{}", command), "PX 45 67");
+        assert_eq!(command, "PX 45 67".parse().unwrap());
+
+        let response = Response::Px(Pixel::new((45, 67).into(), (0x11, 0x22, 0x55).into()));
+        assert_eq!(format!("{}", response), "PX 45 67 112255");
+        assert_eq!(response, "PX 45 67 112255".parse().unwrap());
+        assert!("PX 45 67".parse::<Response>().is_err());
+    }
+
+    #[test]
+    fn offset() {
+        let command = Command::Offset((100, 200).into());
+        assert_eq!(format!("{}", command), "OFFSET 100 200");
+        assert_eq!(command, "OFFSET 100 200".parse().unwrap());
+        assert!("OFFSET 100".parse::<Command>().is_err());
+        assert!("OFFSET 100 200 300".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn help() {
+        assert_eq!(format!("{}", Command::Help), "HELP");
+        assert_eq!(Command::Help, "HELP".parse().unwrap());
+        assert!("HELP me".parse::<Command>().is_err());
+
+        let response = Response::Help("first line\n\n  third line".into());
+        let formatted = format!("{}", response);
+        assert_eq!(formatted, "HELP first line\nHELP \nHELP   third line\nHELP");
+        assert_eq!(response, formatted.parse().unwrap());
+        assert!("HELP first line".parse::<Response>().is_err());
+        assert!("HELP first line\nSIZE 1 2\nHELP"
+            .parse::<Response>()
+            .is_err());
+    }
+
+    #[test]
+    fn custom() {
+        let command = Command::Custom(Box::new(CustomCommand::new(
+            "TEXT".to_string(),
+            vec!["10".to_string(), "20".to_string(), "hello".to_string()],
+        )));
+        assert_eq!(format!("{}", command), "TEXT 10 20 hello");
+        assert_eq!(
+            command,
+            Command::from_bytes_or_custom(b" TEXT 10\t20 hello\n").unwrap()
+        );
+        assert_eq!(
+            Command::from_bytes_or_custom(b"STATS").unwrap(),
+            Command::Custom(Box::new(CustomCommand::new(
+                "STATS".to_string(),
+                Vec::new()
+            )))
+        );
+        assert_eq!(
+            Command::from_bytes_or_custom(b"PX 1 2 ff0000 4")
+                .unwrap_err()
+                .kind(),
+            PixelflutErrorKind::WrongNumberOfArguments
+        );
+        assert!(Command::from_bytes_or_custom(b"TEXT \xff").is_err());
+        assert!(Command::from_bytes_or_custom(b"").is_err());
+
+        // only the server streams fall back to custom commands
+        assert_eq!(
+            "TEXT 10 20 hello".parse::<Command>().unwrap_err().kind(),
+            PixelflutErrorKind::InvalidCommand
+        );
+        assert_eq!(
+            Command::from_bytes(b"STATS").unwrap_err().kind(),
+            PixelflutErrorKind::InvalidCommand
+        );
+
+        let response = Response::Custom("STATS 12 pixels".into());
+        assert_eq!(format!("{}", response), "STATS 12 pixels");
     }
 }
@@ -0,0 +1,506 @@
+//! Buffering and protocol handling of async connections, shared by all async runtimes.
+//!
+//! The runtime modules only implement [`Transport`] for their streams
+//! and wrap the connections in their public types.
+use bytes::{Buf, BytesMut};
+use futures_core::ready;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::io;
+use std::task::{Context, Poll};
+
+use crate::codec::{PixelflutClientCodec, PixelflutServerCodec};
+use crate::command::{
+    get_px_response, Command, CustomCommandHandler, PixelLookup, Response,
+    CUSTOM_COMMAND_MAX_LINE_LENGTH, DEFAULT_HELP_TEXT,
+};
+use crate::error::{PixelflutError, PixelflutErrorKind};
+use crate::error_policy::ErrorCounter;
+use crate::handler::PixelflutHandler;
+use crate::pixel::MAX_FORMATTED_PIXEL_SIZE_NEWLINE;
+use crate::pixel_buffer::PIXEL_BUFFER_DEFAULT_CAPACITY;
+use crate::{Color, Coordinate, ErrorPolicy, Pixel, PixelBuffer, PixelEncoding, PixelflutResult};
+
+/// Default capacity of the read buffer of the async server streams.
+pub static SERVER_READ_BUFFER_DEFAULT_CAPACITY: usize = 2 << 16;
+
+/// Capacity of the read buffer of the async clients.
+///
+/// The server only answers with short lines like `SIZE 800 600`.
+pub(crate) const CLIENT_READ_BUFFER_CAPACITY: usize = 1024;
+
+/// A byte stream of an async runtime.
+pub(crate) trait Transport {
+    /// Reads into the spare capacity of `buf` and returns the number of read bytes.
+    ///
+    /// Zero bytes are read at the end of the stream.
+    fn poll_read_buf(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut BytesMut,
+    ) -> Poll<io::Result<usize>>;
+
+    fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>;
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+/// Writes the whole buffer to the transport and removes the written bytes.
+fn poll_write_buf(
+    transport: &mut impl Transport,
+    cx: &mut Context<'_>,
+    buf: &mut BytesMut,
+) -> Poll<PixelflutResult<()>> {
+    while !buf.is_empty() {
+        let written = ready!(transport.poll_write(cx, buf))?;
+        if written == 0 {
+            return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()));
+        }
+        buf.advance(written);
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// Server side of an async connection.
+pub(crate) struct ServerConnection<T> {
+    pub(crate) transport: T,
+    codec: PixelflutServerCodec,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    dimensions: (u32, u32),
+    pixel_lookup: Option<PixelLookup>,
+    offset: Coordinate,
+    help: Cow<'static, str>,
+    error_policy: ErrorPolicy,
+    errors: ErrorCounter,
+    custom_commands: HashMap<String, CustomCommandHandler>,
+    flush_pending: bool,
+    pending_command: Option<Command>,
+    pending_error: Option<PixelflutError>,
+}
+
+impl<T: Transport> ServerConnection<T> {
+    pub(crate) fn new(transport: T, dimensions: (u32, u32), capacity: usize) -> Self {
+        ServerConnection {
+            transport,
+            codec: PixelflutServerCodec::new(),
+            read_buf: BytesMut::with_capacity(capacity),
+            write_buf: BytesMut::new(),
+            dimensions,
+            pixel_lookup: None,
+            offset: Coordinate::default(),
+            help: Cow::Borrowed(DEFAULT_HELP_TEXT),
+            error_policy: ErrorPolicy::default(),
+            errors: ErrorCounter::default(),
+            custom_commands: HashMap::new(),
+            flush_pending: false,
+            pending_command: None,
+            pending_error: None,
+        }
+    }
+
+    pub(crate) fn set_help(&mut self, help: Cow<'static, str>) {
+        self.help = help;
+    }
+
+    pub(crate) fn offset(&self) -> Coordinate {
+        self.offset
+    }
+
+    pub(crate) fn set_pixel_lookup(
+        &mut self,
+        lookup: impl Fn(Coordinate) -> Option<Color> + Send + Sync + 'static,
+    ) {
+        self.pixel_lookup = Some(Box::new(lookup));
+    }
+
+    pub(crate) fn set_error_policy(&mut self, error_policy: ErrorPolicy) {
+        self.error_policy = error_policy;
+    }
+
+    pub(crate) fn error_count(&self) -> u64 {
+        self.errors.errors()
+    }
+
+    pub(crate) fn register_command(&mut self, verb: String, handler: CustomCommandHandler) {
+        self.custom_commands.insert(verb, handler);
+        self.allow_custom_commands();
+    }
+
+    /// Raises the line limit, so it fits custom commands.
+    fn allow_custom_commands(&mut self) {
+        let max_line_length = self.codec.max_line_length();
+        self.codec
+            .set_max_line_length(max_line_length.max(CUSTOM_COMMAND_MAX_LINE_LENGTH));
+    }
+
+    pub(crate) fn set_max_line_length(&mut self, max_line_length: usize) {
+        self.codec.set_max_line_length(max_line_length);
+    }
+
+    /// Decodes the next buffered command and queues an `ERROR` for invalid commands.
+    ///
+    /// Invalid commands are skipped, as long as the error policy allows it.
+    /// Otherwise the error is kept until the queued responses are written.
+    fn next_buffered_command(&mut self) -> PixelflutResult<Option<Command>> {
+        while self.pending_error.is_none() {
+            match self.codec.decode_command(&mut self.read_buf) {
+                Ok(Some(command)) => {
+                    self.errors.record_line();
+                    return Ok(Some(command));
+                }
+                Err(err) if err.kind() != PixelflutErrorKind::Io => {
+                    self.errors.record_line();
+                    self.handle_invalid_line(err)?;
+                }
+                result => return result,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Queues an `ERROR` for an invalid line.
+    ///
+    /// If the error policy does not allow to skip the line,
+    /// the error is returned after the queued responses are written.
+    fn handle_invalid_line(&mut self, err: PixelflutError) -> PixelflutResult<()> {
+        self.queue_response(&Response::Error(err.to_string().into()))?;
+        if self.errors.record_error(&self.error_policy) {
+            self.pending_error = Some(err);
+        }
+        Ok(())
+    }
+
+    /// Queues a response, that is written before the next read.
+    fn queue_response(&mut self, response: &Response) -> PixelflutResult<()> {
+        self.codec.encode_response(response, &mut self.write_buf)?;
+        self.flush_pending = true;
+        Ok(())
+    }
+
+    /// Writes and flushes all queued responses.
+    ///
+    /// Returns a pending error afterwards.
+    fn poll_write_responses(&mut self, cx: &mut Context<'_>) -> Poll<PixelflutResult<()>> {
+        ready!(poll_write_buf(&mut self.transport, cx, &mut self.write_buf))?;
+        if self.flush_pending {
+            ready!(self.transport.poll_flush(cx))?;
+            self.flush_pending = false;
+        }
+        match self.pending_error.take() {
+            Some(err) => Poll::Ready(Err(err)),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    async fn write_responses(&mut self) -> PixelflutResult<()> {
+        poll_fn(|cx| self.poll_write_responses(cx)).await
+    }
+
+    /// Reads more data from the stream into the read buffer.
+    ///
+    /// Returns `false` on a clean end of the stream.
+    fn poll_fill_read_buf(&mut self, cx: &mut Context<'_>) -> Poll<PixelflutResult<bool>> {
+        if ready!(self.transport.poll_read_buf(cx, &mut self.read_buf))? == 0 {
+            if self.read_buf.is_empty() {
+                Poll::Ready(Ok(false))
+            } else {
+                Poll::Ready(Err(
+                    PixelflutErrorKind::Io.with_description("Unexpected end of stream")
+                ))
+            }
+        } else {
+            Poll::Ready(Ok(true))
+        }
+    }
+
+    /// Writes the queued responses and reads the next command.
+    fn poll_read_command(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<PixelflutResult<Option<Command>>> {
+        loop {
+            ready!(self.poll_write_responses(cx))?;
+            if let Some(command) = self.pending_command.take() {
+                return Poll::Ready(Ok(Some(command)));
+            }
+            if let Some(command) = self.next_buffered_command()? {
+                if self.write_buf.is_empty() {
+                    return Poll::Ready(Ok(Some(command)));
+                }
+                // answer the skipped invalid lines before returning the command
+                self.pending_command = Some(command);
+                continue;
+            }
+            if self.pending_error.is_none() && !ready!(self.poll_fill_read_buf(cx))? {
+                ready!(self.poll_write_responses(cx))?;
+                return Poll::Ready(Ok(None));
+            }
+        }
+    }
+
+    async fn read_command(&mut self) -> PixelflutResult<Option<Command>> {
+        poll_fn(|cx| self.poll_read_command(cx)).await
+    }
+
+    /// Handles a command and returns the pixel, if the command sets a pixel.
+    ///
+    /// The answers to all other commands are queued.
+    fn handle_command(&mut self, command: Command) -> PixelflutResult<Option<Pixel>> {
+        self.handle_command_with(command, |_, _| None)
+    }
+
+    /// Like [`handle_command`](Self::handle_command),
+    /// but custom commands without a registered handler are passed to `unknown`.
+    fn handle_command_with(
+        &mut self,
+        command: Command,
+        mut unknown: impl FnMut(&str, &[String]) -> Option<Response>,
+    ) -> PixelflutResult<Option<Pixel>> {
+        match command {
+            Command::Px(pixel) => {
+                // pixels translated beyond the coordinate range are dropped
+                return Ok(pixel
+                    .position
+                    .checked_add(self.offset)
+                    .map(|position| Pixel::new(position, pixel.color)));
+            }
+            Command::Size => self.queue_response(&Response::Size {
+                w: self.dimensions.0,
+                h: self.dimensions.1,
+            })?,
+            Command::GetPx(position) => {
+                let response = get_px_response(self.pixel_lookup.as_ref(), position, self.offset);
+                self.queue_response(&response)?
+            }
+            Command::Offset(offset) => self.offset = offset,
+            Command::Help => self.queue_response(&Response::Help(self.help.clone()))?,
+            Command::Custom(custom) => {
+                let mut responses = Vec::new();
+                let result = match self.custom_commands.get_mut(&custom.verb) {
+                    Some(handler) => handler(&custom.args, &mut responses),
+                    None => match unknown(&custom.verb, &custom.args) {
+                        Some(response) => {
+                            responses.push(response);
+                            Ok(())
+                        }
+                        None => Err(PixelflutErrorKind::InvalidCommand.into()),
+                    },
+                };
+                match result {
+                    Ok(()) => {
+                        for response in &responses {
+                            self.queue_response(response)?;
+                        }
+                    }
+                    Err(err) => self.handle_invalid_line(err)?,
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Polls for the next pixel, answering all other commands along the way.
+    pub(crate) fn poll_read_pixel(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<PixelflutResult<Option<Pixel>>> {
+        while let Some(command) = ready!(self.poll_read_command(cx))? {
+            if let Some(pixel) = self.handle_command(command)? {
+                return Poll::Ready(Ok(Some(pixel)));
+            }
+        }
+        Poll::Ready(Ok(None))
+    }
+
+    pub(crate) async fn read_pixel(&mut self) -> PixelflutResult<Option<Pixel>> {
+        poll_fn(|cx| self.poll_read_pixel(cx)).await
+    }
+
+    pub(crate) async fn read_pixels_with(
+        &mut self,
+        mut f: impl FnMut(Pixel),
+    ) -> PixelflutResult<Option<usize>> {
+        let mut pixels = 0;
+        let mut command = match self.read_command().await? {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        loop {
+            if let Some(pixel) = self.handle_command(command)? {
+                f(pixel);
+                pixels += 1;
+            }
+            command = match self.next_buffered_command()? {
+                Some(command) => command,
+                None => break,
+            };
+        }
+        self.write_responses().await?;
+        Ok(Some(pixels))
+    }
+
+    pub(crate) async fn run_handler(
+        &mut self,
+        handler: &mut impl PixelflutHandler,
+    ) -> PixelflutResult<()> {
+        self.allow_custom_commands();
+        handler.on_connect();
+        let result = self.dispatch(handler).await;
+        handler.on_disconnect(result.as_ref().err());
+        result
+    }
+
+    async fn dispatch(&mut self, handler: &mut impl PixelflutHandler) -> PixelflutResult<()> {
+        while let Some(command) = self.read_command().await? {
+            match handler.on_command(&command) {
+                Some(response) => self.queue_response(&response)?,
+                None => {
+                    let unknown =
+                        |verb: &str, args: &[String]| handler.on_unknown_command(verb, args);
+                    if let Some(pixel) = self.handle_command_with(command, unknown)? {
+                        handler.on_pixel(pixel);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Client side of an async connection.
+pub(crate) struct ClientConnection<T> {
+    pub(crate) transport: T,
+    codec: PixelflutClientCodec,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+}
+
+impl<T: Transport> ClientConnection<T> {
+    pub(crate) fn new(transport: T) -> Self {
+        ClientConnection {
+            transport,
+            codec: PixelflutClientCodec::new(),
+            read_buf: BytesMut::with_capacity(CLIENT_READ_BUFFER_CAPACITY),
+            write_buf: BytesMut::with_capacity(PIXEL_BUFFER_DEFAULT_CAPACITY),
+        }
+    }
+
+    pub(crate) fn set_encoding(&mut self, encoding: PixelEncoding) {
+        self.codec.set_encoding(encoding);
+    }
+
+    /// Writes a command after the buffered pixels and flushes the stream.
+    async fn write_command(&mut self, command: &Command) -> PixelflutResult<()> {
+        self.codec.encode_command(command, &mut self.write_buf)?;
+        self.flush().await
+    }
+
+    async fn read_response(&mut self) -> PixelflutResult<Response> {
+        loop {
+            if let Some(response) = self.codec.decode_response(&mut self.read_buf)? {
+                return Ok(response);
+            }
+            let (transport, read_buf) = (&mut self.transport, &mut self.read_buf);
+            if poll_fn(|cx| transport.poll_read_buf(cx, read_buf)).await? == 0 {
+                return Err(PixelflutErrorKind::Io.with_description("Unexpected end of stream"));
+            }
+        }
+    }
+
+    pub(crate) async fn set(&mut self, pixel: Pixel) -> PixelflutResult<()> {
+        if self.is_capacity_reached() {
+            self.write_buffered().await?;
+        }
+        self.codec
+            .encode_command(&Command::Px(pixel), &mut self.write_buf)
+    }
+
+    pub(crate) async fn dimensions(&mut self) -> PixelflutResult<(u32, u32)> {
+        self.write_command(&Command::Size).await?;
+        let response = self.read_response().await?;
+        Ok(match response {
+            Response::Size { w, h } => (w, h),
+            Response::Error(_err) => return Err(PixelflutErrorKind::ServerError.into()),
+            _ => return Err(PixelflutErrorKind::State.into()),
+        })
+    }
+
+    pub(crate) async fn get(&mut self, x: u32, y: u32) -> PixelflutResult<Color> {
+        self.write_command(&Command::GetPx((x, y).into())).await?;
+        let response = self.read_response().await?;
+        Ok(match response {
+            Response::Px(pixel) if pixel.position == (x, y).into() => pixel.color,
+            Response::Error(_err) => return Err(PixelflutErrorKind::ServerError.into()),
+            _ => return Err(PixelflutErrorKind::State.into()),
+        })
+    }
+
+    pub(crate) async fn help(&mut self) -> PixelflutResult<String> {
+        self.write_command(&Command::Help).await?;
+        let response = self.read_response().await?;
+        Ok(match response {
+            Response::Help(text) => text.into_owned(),
+            Response::Error(_err) => return Err(PixelflutErrorKind::ServerError.into()),
+            _ => return Err(PixelflutErrorKind::State.into()),
+        })
+    }
+
+    pub(crate) async fn set_offset(&mut self, x: u32, y: u32) -> PixelflutResult<()> {
+        self.write_command(&Command::Offset((x, y).into())).await
+    }
+
+    pub(crate) async fn write_buffer(&mut self, buffer: &PixelBuffer) -> PixelflutResult<()> {
+        self.write_buffered().await?;
+        let mut buf = buffer.as_slice();
+        while !buf.is_empty() {
+            let transport = &mut self.transport;
+            let written = poll_fn(|cx| transport.poll_write(cx, buf)).await?;
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            buf = &buf[written..];
+        }
+        Ok(())
+    }
+
+    async fn write_buffered(&mut self) -> PixelflutResult<()> {
+        let (transport, write_buf) = (&mut self.transport, &mut self.write_buf);
+        poll_fn(|cx| poll_write_buf(transport, cx, write_buf)).await
+    }
+
+    /// Returns `true`, if the internal buffer has to be written before another pixel is added.
+    fn is_capacity_reached(&self) -> bool {
+        self.write_buf.len() + MAX_FORMATTED_PIXEL_SIZE_NEWLINE > PIXEL_BUFFER_DEFAULT_CAPACITY
+    }
+
+    pub(crate) async fn flush(&mut self) -> PixelflutResult<()> {
+        poll_fn(|cx| self.poll_flush(cx)).await
+    }
+
+    /// Makes room for another pixel in the internal buffer.
+    pub(crate) fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<PixelflutResult<()>> {
+        if self.is_capacity_reached() {
+            ready!(poll_write_buf(&mut self.transport, cx, &mut self.write_buf))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    pub(crate) fn start_send(&mut self, pixel: Pixel) -> PixelflutResult<()> {
+        self.codec
+            .encode_command(&Command::Px(pixel), &mut self.write_buf)
+    }
+
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<PixelflutResult<()>> {
+        ready!(poll_write_buf(&mut self.transport, cx, &mut self.write_buf))?;
+        Poll::Ready(Ok(ready!(self.transport.poll_flush(cx))?))
+    }
+
+    pub(crate) fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<PixelflutResult<()>> {
+        ready!(self.poll_flush(cx))?;
+        Poll::Ready(Ok(ready!(self.transport.poll_shutdown(cx))?))
+    }
+}
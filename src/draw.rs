@@ -0,0 +1,819 @@
+//! Drawing primitives producing clipped pixels.
+use crate::{Color, Coordinate, Pixel, Rect, Text};
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::convert::TryFrom;
+use core::iter;
+
+/// Draws shapes, clipped to the dimensions of a canvas.
+///
+/// Every shape is returned as an iterator of [`Pixel`]s,
+/// so it can be passed to any pixel sink:
+/// the `set_pixels` method of the sync and async clients,
+/// [`PixelBuffer`](crate::PixelBuffer) via `extend` or `collect`,
+/// or the `Sink` implementation of the async clients.
+/// Every pixel of a shape is only returned once,
+/// so colors with an alpha channel are blended evenly.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pixelflut::sync::PixelflutClient;
+/// use pixelflut::{Coordinate, Painter, Rect};
+///
+/// fn main() -> pixelflut::PixelflutResult<()> {
+///     let mut client = PixelflutClient::connect("127.0.0.1:1337")?;
+///     let painter = Painter::new(client.dimensions()?);
+///     client.set_pixels(painter.fill_rect(Rect::new(10, 10, 100, 50), (255, 0, 0)))?;
+///     client.set_pixels(painter.circle(Coordinate::new(60, 35), 20, (255, 255, 255)))?;
+///     client.flush()
+/// }
+/// ```
+#[derive(Copy, Clone, PartialEq, Hash, Debug)]
+pub struct Painter {
+    clip: Rect,
+}
+
+impl Painter {
+    /// Creates a `Painter` for a canvas with the given dimensions,
+    /// e.g. as reported by the server.
+    pub fn new(dimensions: (u32, u32)) -> Painter {
+        Painter::with_clip(Rect::from_dimensions(dimensions))
+    }
+
+    /// Creates a `Painter` that only draws inside of `clip`.
+    pub fn with_clip(clip: Rect) -> Painter {
+        Painter { clip }
+    }
+
+    /// Returns the region shapes are clipped to.
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    /// Returns the pixel at the position, if it is inside of the clip region.
+    fn pixel(&self, x: i64, y: i64, color: Color) -> Option<Pixel> {
+        let position = Coordinate::new(u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        if self.clip.contains(position) {
+            Some(Pixel::new(position, color))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the part of the row `y` from `start` to `end` (exclusive)
+    /// that is inside of the clip region.
+    fn span(&self, y: i64, start: i64, end: i64) -> Option<Rect> {
+        let y = u32::try_from(y).ok()?;
+        let start = start.clamp(0, i64::from(u32::MAX)) as u32;
+        let end = end.clamp(0, i64::from(u32::MAX)) as u32;
+        Rect::new(start, y, end.saturating_sub(start), 1).intersection(self.clip)
+    }
+
+    /// Fills a rectangle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Painter, Rect};
+    /// let painter = Painter::new((800, 600));
+    /// assert_eq!(painter.fill_rect(Rect::new(790, 0, 20, 20), (255, 0, 0)).count(), 200);
+    /// ```
+    pub fn fill_rect(
+        &self,
+        rect: Rect,
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone {
+        let color = color.into();
+        rect.intersection(self.clip)
+            .into_iter()
+            .flatten()
+            .map(move |position| Pixel::new(position, color))
+    }
+
+    /// Draws the outline of a rectangle.
+    ///
+    /// The outline covers the outermost rows and columns of the rectangle.
+    pub fn stroke_rect(
+        &self,
+        rect: Rect,
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone {
+        let color = color.into();
+        let clip = self.clip;
+        let (x, y) = rect.origin().into();
+        let (width, height) = rect.dimensions();
+        // the bottom row and the right column only exist, if they are not the top row or left column
+        let bottom = u32::from(height > 1);
+        let right = u32::from(width > 1);
+        let inner_height = height.saturating_sub(2);
+        let edges = [
+            Rect::new(x, y, width, min(height, 1)),
+            Rect::new(x, rect.bottom().saturating_sub(1), width, bottom),
+            Rect::new(x, y.saturating_add(1), min(width, 1), inner_height),
+            Rect::new(
+                rect.right().saturating_sub(1),
+                y.saturating_add(1),
+                right,
+                inner_height,
+            ),
+        ];
+        IntoIterator::into_iter(edges)
+            .filter_map(move |edge| edge.intersection(clip))
+            .flatten()
+            .map(move |position| Pixel::new(position, color))
+    }
+
+    /// Draws a line with Bresenham's algorithm, including both end points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Painter};
+    /// let painter = Painter::new((800, 600));
+    /// let line: Vec<(u32, u32)> = painter
+    ///     .line(Coordinate::new(0, 0), Coordinate::new(3, 1), (255, 0, 0))
+    ///     .map(|pixel| pixel.position.into())
+    ///     .collect();
+    /// assert_eq!(line, [(0, 0), (1, 0), (2, 1), (3, 1)]);
+    /// ```
+    pub fn line(
+        &self,
+        from: Coordinate,
+        to: Coordinate,
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone {
+        let color = color.into();
+        let painter = *self;
+        Bresenham::new(from, to).filter_map(move |(x, y)| painter.pixel(x, y, color))
+    }
+
+    /// Draws the outline of a circle with the midpoint circle algorithm.
+    pub fn circle(
+        &self,
+        center: Coordinate,
+        radius: u32,
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone {
+        let color = color.into();
+        let painter = *self;
+        let (cx, cy) = (i64::from(center.x), i64::from(center.y));
+        CircleOctant::new(radius)
+            .flat_map(|(x, y)| {
+                // the mirrored points, without the ones that fall onto each other
+                let mut points = [(0, 0); 8];
+                let mut len = 0;
+                for &(dx, dy) in &[(x, y), (y, x)] {
+                    for &(sx, sy) in &[(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                        let point = (dx * sx, dy * sy);
+                        if !points[..len].contains(&point) {
+                            points[len] = point;
+                            len += 1;
+                        }
+                    }
+                }
+                IntoIterator::into_iter(points).take(len)
+            })
+            .filter_map(move |(dx, dy)| painter.pixel(cx + dx, cy + dy, color))
+    }
+
+    /// Fills a circle.
+    ///
+    /// The filled circle covers the same pixels as its outline and everything inside.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Painter};
+    /// let painter = Painter::new((800, 600));
+    /// assert_eq!(painter.fill_circle(Coordinate::new(10, 10), 2, (255, 0, 0)).count(), 21);
+    /// ```
+    pub fn fill_circle(
+        &self,
+        center: Coordinate,
+        radius: u32,
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone {
+        let color = color.into();
+        let painter = *self;
+        let (cx, cy) = (i64::from(center.x), i64::from(center.y));
+        // only the rows inside of the clip region are calculated
+        let radius = i64::from(radius);
+        let top = max(-radius, i64::from(self.clip.origin().y) - cy);
+        let bottom = min(radius, i64::from(self.clip.bottom()) - 1 - cy);
+        (top..=bottom)
+            .filter_map(move |dy| {
+                let half_width = circle_half_width(radius, dy.abs());
+                painter.span(cy + dy, cx - half_width, cx + half_width + 1)
+            })
+            .flatten()
+            .map(move |position| Pixel::new(position, color))
+    }
+
+    /// Draws the closed outline of a polygon.
+    ///
+    /// Pixels shared by several edges, e.g. at acute corners, are only returned once.
+    pub fn polygon<'a>(
+        &self,
+        points: &'a [Coordinate],
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone + 'a {
+        let color = color.into();
+        let painter = *self;
+        let edges = points.iter().zip(points.iter().cycle().skip(1));
+        // edges meeting at an acute angle or crossing each other share pixels
+        let mut seen = BTreeSet::new();
+        // a single point is drawn as a point, not as an empty edge
+        let single = match points {
+            [point] => Some(*point),
+            _ => None,
+        };
+        edges
+            .flat_map(|(from, to)| {
+                // the end point is the start of the next edge, so it is skipped
+                let bresenham = Bresenham::new(*from, *to);
+                let len = bresenham.len - 1;
+                bresenham.take(len as usize)
+            })
+            .chain(single.map(|point| (i64::from(point.x), i64::from(point.y))))
+            .filter_map(move |(x, y)| painter.pixel(x, y, color))
+            .filter(move |pixel| seen.insert((pixel.position.x, pixel.position.y)))
+    }
+
+    /// Fills a polygon with the even-odd rule.
+    ///
+    /// Pixels on the left and top edges are filled, pixels on the right and bottom edges are not,
+    /// so polygons sharing an edge do not overlap.
+    /// Draw the [`polygon`](Self::polygon) as well, to include all edges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Painter};
+    /// let painter = Painter::new((800, 600));
+    /// let square = [(0, 0).into(), (4, 0).into(), (4, 4).into(), (0, 4).into()];
+    /// assert_eq!(painter.fill_polygon(&square, (255, 0, 0)).count(), 16);
+    /// ```
+    pub fn fill_polygon<'a>(
+        &self,
+        points: &'a [Coordinate],
+        color: impl Into<Color>,
+    ) -> impl Iterator<Item = Pixel> + Clone + 'a {
+        let color = color.into();
+        let painter = *self;
+        let top = points.iter().map(|point| point.y).min().unwrap_or(0);
+        let bottom = points.iter().map(|point| point.y).max().unwrap_or(0);
+        let top = max(i64::from(top), i64::from(self.clip.origin().y));
+        let bottom = min(i64::from(bottom), i64::from(self.clip.bottom()));
+        (top..bottom)
+            .flat_map(move |y| {
+                let mut crossings = scanline_crossings(points, y);
+                crossings.sort_unstable();
+                let spans: Vec<Rect> = crossings
+                    .chunks_exact(2)
+                    .filter_map(|pair| painter.span(y, pair[0], pair[1]))
+                    .collect();
+                spans.into_iter().flatten()
+            })
+            .map(move |position| Pixel::new(position, color))
+    }
+
+    /// Draws text, see [`Text`].
+    pub fn text<'a>(&self, text: &Text<'a>) -> impl Iterator<Item = Pixel> + Clone + 'a {
+        let clip = self.clip;
+        text.pixels()
+            .filter(move |pixel| clip.contains(pixel.position))
+    }
+
+    /// Fills the area around `start` that has the same color as `start`.
+    ///
+    /// The current colors are read with `lookup`, e.g. from a [`Canvas`](crate::Canvas)
+    /// or an image fetched before; `None` is treated as a border.
+    /// Neighbours are connected horizontally and vertically.
+    /// Colors are compared with alpha channels of 255 being equal to no alpha channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Canvas, Color, Coordinate, Painter, Rect};
+    /// let canvas = Canvas::new(10, 10);
+    /// let painter = Painter::new(canvas.dimensions());
+    /// for pixel in painter.stroke_rect(Rect::new(2, 2, 5, 5), (255, 255, 255)) {
+    ///     canvas.set(&pixel);
+    /// }
+    /// let fill = painter.flood_fill(Coordinate::new(4, 4), (255, 0, 0), |position| canvas.get(position));
+    /// assert_eq!(fill.count(), 9);
+    /// ```
+    pub fn flood_fill<F: FnMut(Coordinate) -> Option<Color>>(
+        &self,
+        start: Coordinate,
+        color: impl Into<Color>,
+        lookup: F,
+    ) -> FloodFill<F> {
+        FloodFill::new(self.clip, start, color.into(), lookup)
+    }
+}
+
+/// Returns the first pixel right of every edge crossing row `y`.
+///
+/// The vertices are at the pixel centers. Each edge covers the rows from its
+/// upper end up to, but not including, its lower end.
+fn scanline_crossings(points: &[Coordinate], y: i64) -> Vec<i64> {
+    let edges = points.iter().zip(points.iter().cycle().skip(1));
+    edges
+        .filter_map(|(a, b)| {
+            let (ax, ay) = (i128::from(a.x), i128::from(a.y));
+            let (bx, by) = (i128::from(b.x), i128::from(b.y));
+            let y = i128::from(y);
+            if (ay <= y) == (by <= y) {
+                return None;
+            }
+            // x = ax + (y - ay) * (bx - ax) / (by - ay), rounded up
+            let (numerator, denominator) = if by > ay {
+                (ax * (by - ay) + (y - ay) * (bx - ax), by - ay)
+            } else {
+                (ax * (ay - by) + (y - ay) * (ax - bx), ay - by)
+            };
+            let x = numerator.div_euclid(denominator)
+                + i128::from(numerator.rem_euclid(denominator) != 0);
+            Some(x as i64)
+        })
+        .collect()
+}
+
+/// Points of a line, calculated with Bresenham's algorithm.
+#[derive(Clone, Debug)]
+struct Bresenham {
+    x: i64,
+    y: i64,
+    dx: i64,
+    dy: i64,
+    step_x: i64,
+    step_y: i64,
+    error: i64,
+    /// The number of points, that are left.
+    len: u64,
+}
+
+impl Bresenham {
+    fn new(from: Coordinate, to: Coordinate) -> Bresenham {
+        let (x, y) = (i64::from(from.x), i64::from(from.y));
+        let dx = (i64::from(to.x) - x).abs();
+        let dy = -(i64::from(to.y) - y).abs();
+        Bresenham {
+            x,
+            y,
+            dx,
+            dy,
+            step_x: if to.x >= from.x { 1 } else { -1 },
+            step_y: if to.y >= from.y { 1 } else { -1 },
+            error: dx + dy,
+            len: max(dx, -dy) as u64 + 1,
+        }
+    }
+}
+
+impl Iterator for Bresenham {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<(i64, i64)> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let point = (self.x, self.y);
+        let doubled = 2 * self.error;
+        if doubled >= self.dy {
+            self.error += self.dy;
+            self.x += self.step_x;
+        }
+        if doubled <= self.dx {
+            self.error += self.dx;
+            self.y += self.step_y;
+        }
+        Some(point)
+    }
+}
+
+/// Points `(x, y)` with `x <= y` of one eighth of a circle around the origin,
+/// calculated with the midpoint circle algorithm.
+#[derive(Clone, Debug)]
+struct CircleOctant {
+    x: i64,
+    y: i64,
+    decision: i64,
+}
+
+impl CircleOctant {
+    fn new(radius: u32) -> CircleOctant {
+        CircleOctant {
+            x: 0,
+            y: i64::from(radius),
+            decision: 1 - i64::from(radius),
+        }
+    }
+}
+
+impl Iterator for CircleOctant {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<(i64, i64)> {
+        if self.x > self.y {
+            return None;
+        }
+        let point = (self.x, self.y);
+        self.x += 1;
+        if self.decision < 0 {
+            self.decision += 2 * self.x + 1;
+        } else {
+            self.y -= 1;
+            self.decision += 2 * (self.x - self.y) + 1;
+        }
+        Some(point)
+    }
+}
+
+/// Half width of the row `dy` of a circle around the origin, with `0 <= dy <= radius`.
+///
+/// Matches the outline of [`CircleOctant`] mirrored to all octants,
+/// without calculating the other rows.
+fn circle_half_width(radius: i64, dy: i64) -> i64 {
+    if radius == 0 {
+        return 0;
+    }
+    // `y` of the octant point at `x`, the octant ends at the first `x > y`
+    let octant_y = |x: i64| largest_below(i128::from(radius).pow(2) - i128::from(x).pow(2), 1);
+    // the mirrored octant point `(dy, y)` spans the row up to `y`
+    let mirrored = octant_y(dy).filter(|&y| y >= dy);
+    // the largest `x` of the octant points `(x, dy)`
+    let direct = largest_below(
+        i128::from(radius).pow(2) - i128::from(dy) * i128::from(dy - 1),
+        0,
+    )
+    .filter(|&x| x <= dy && octant_y(x) == Some(dy));
+    max(mirrored, direct).unwrap_or(0)
+}
+
+/// Returns the largest `v >= 0` with `v * (v - offset) < limit`.
+fn largest_below(limit: i128, offset: i128) -> Option<i64> {
+    if limit <= 0 {
+        return None;
+    }
+    // the result is below `2^33` for all radii up to `u32::MAX`
+    let (mut low, mut high) = (0i128, 1i128 << 34);
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        if mid * (mid - offset) < limit {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some(low as i64)
+}
+
+/// Pixels of a flood fill.
+///
+/// Created by [`Painter::flood_fill`].
+pub struct FloodFill<F> {
+    clip: Rect,
+    color: Color,
+    target: Option<(u8, u8, u8, u8)>,
+    lookup: F,
+    stack: Vec<Coordinate>,
+    /// One bit for every coordinate in the clip region, set once it was put on the stack.
+    visited: Vec<u64>,
+}
+
+impl<F: FnMut(Coordinate) -> Option<Color>> FloodFill<F> {
+    fn new(clip: Rect, start: Coordinate, color: Color, mut lookup: F) -> FloodFill<F> {
+        let target = if clip.contains(start) {
+            lookup(start).map(Color::normalized)
+        } else {
+            None
+        };
+        let mut fill = FloodFill {
+            clip,
+            color,
+            target,
+            lookup,
+            stack: Vec::new(),
+            visited: Vec::new(),
+        };
+        if target.is_some() {
+            fill.visited = vec![0; clip.area().div_ceil(64) as usize];
+            fill.visit(start);
+        }
+        fill
+    }
+
+    /// Puts the coordinate on the stack, if it is inside and was not visited before.
+    fn visit(&mut self, position: Coordinate) {
+        if !self.clip.contains(position) {
+            return;
+        }
+        let origin = self.clip.origin();
+        let index = u64::from(position.y - origin.y) * u64::from(self.clip.width())
+            + u64::from(position.x - origin.x);
+        let (word, bit) = ((index / 64) as usize, 1 << (index % 64));
+        if self.visited[word] & bit == 0 {
+            self.visited[word] |= bit;
+            self.stack.push(position);
+        }
+    }
+}
+
+impl<F: FnMut(Coordinate) -> Option<Color>> Iterator for FloodFill<F> {
+    type Item = Pixel;
+
+    fn next(&mut self) -> Option<Pixel> {
+        while let Some(position) = self.stack.pop() {
+            if (self.lookup)(position).map(Color::normalized) != self.target {
+                continue;
+            }
+            let (x, y) = position.into();
+            if let Some(x) = x.checked_sub(1) {
+                self.visit(Coordinate::new(x, y));
+            }
+            if let Some(y) = y.checked_sub(1) {
+                self.visit(Coordinate::new(x, y));
+            }
+            if let Some(x) = x.checked_add(1) {
+                self.visit(Coordinate::new(x, y));
+            }
+            if let Some(y) = y.checked_add(1) {
+                self.visit(Coordinate::new(x, y));
+            }
+            return Some(Pixel::new(position, self.color));
+        }
+        None
+    }
+}
+
+impl<F: FnMut(Coordinate) -> Option<Color>> iter::FusedIterator for FloodFill<F> {}
+
+#[cfg(test)]
+mod test {
+    use super::Painter;
+    use crate::{Canvas, Color, Coordinate, Pixel, Rect};
+    use alloc::vec::Vec;
+
+    fn positions(pixels: impl Iterator<Item = Pixel>) -> Vec<(u32, u32)> {
+        let mut positions: Vec<(u32, u32)> = pixels.map(|pixel| pixel.position.into()).collect();
+        positions.sort_unstable_by_key(|&(x, y)| (y, x));
+        positions
+    }
+
+    fn assert_unique(positions: &[(u32, u32)]) {
+        let mut deduplicated = positions.to_vec();
+        deduplicated.dedup();
+        assert_eq!(deduplicated, positions);
+    }
+
+    #[test]
+    fn rects() {
+        let painter = Painter::new((10, 10));
+        assert_eq!(
+            positions(painter.fill_rect(Rect::new(8, 8, 5, 5), (1, 2, 3))),
+            [(8, 8), (9, 8), (8, 9), (9, 9)]
+        );
+        assert_eq!(
+            painter.fill_rect(Rect::new(10, 0, 5, 5), (1, 2, 3)).count(),
+            0
+        );
+
+        let outline = positions(painter.stroke_rect(Rect::new(1, 1, 3, 3), (1, 2, 3)));
+        assert_eq!(
+            outline,
+            [
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (1, 2),
+                (3, 2),
+                (1, 3),
+                (2, 3),
+                (3, 3)
+            ]
+        );
+        assert_eq!(
+            positions(painter.stroke_rect(Rect::new(1, 1, 3, 1), (1, 2, 3))),
+            [(1, 1), (2, 1), (3, 1)]
+        );
+        assert_eq!(
+            positions(painter.stroke_rect(Rect::new(1, 1, 1, 2), (1, 2, 3))),
+            [(1, 1), (1, 2)]
+        );
+        assert_eq!(
+            painter
+                .stroke_rect(Rect::new(1, 1, 0, 2), (1, 2, 3))
+                .count(),
+            0
+        );
+        assert_eq!(
+            painter
+                .stroke_rect(Rect::new(8, 8, 5, 5), (1, 2, 3))
+                .count(),
+            3
+        );
+
+        // the rows below the top row start beyond the coordinate range
+        let unclipped = Painter::with_clip(Rect::new(0, 0, u32::MAX, u32::MAX));
+        assert_eq!(
+            unclipped
+                .stroke_rect(Rect::new(5, u32::MAX, 3, 3), (1, 2, 3))
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let painter = Painter::new((10, 10));
+        let color = Color::rgb(1, 2, 3);
+        let line: Vec<Pixel> = painter
+            .line(Coordinate::new(3, 0), Coordinate::new(0, 3), color)
+            .collect();
+        assert_eq!(
+            line,
+            [
+                Pixel::from(((3, 0), color)),
+                Pixel::from(((2, 1), color)),
+                Pixel::from(((1, 2), color)),
+                Pixel::from(((0, 3), color)),
+            ]
+        );
+        assert_eq!(
+            positions(painter.line(Coordinate::new(5, 5), Coordinate::new(5, 5), color)),
+            [(5, 5)]
+        );
+        // clipped to the canvas
+        assert_eq!(
+            painter
+                .line(Coordinate::new(0, 5), Coordinate::new(100, 5), color)
+                .count(),
+            10
+        );
+        // symmetric
+        assert_eq!(
+            positions(painter.line(Coordinate::new(0, 1), Coordinate::new(7, 4), color)),
+            positions(painter.line(Coordinate::new(7, 4), Coordinate::new(0, 1), color))
+        );
+    }
+
+    #[test]
+    fn circles() {
+        let painter = Painter::new((100, 100));
+        let center = Coordinate::new(50, 50);
+        assert_eq!(positions(painter.circle(center, 0, (1, 2, 3))), [(50, 50)]);
+        assert_eq!(
+            positions(painter.circle(center, 1, (1, 2, 3))),
+            [(50, 49), (49, 50), (51, 50), (50, 51)]
+        );
+
+        for radius in 0..20 {
+            let outline = positions(painter.circle(center, radius, (1, 2, 3)));
+            assert_unique(&outline);
+            let filled = positions(painter.fill_circle(center, radius, (1, 2, 3)));
+            assert_unique(&filled);
+            for position in &outline {
+                assert!(filled.contains(position), "{} {:?}", radius, position);
+            }
+        }
+
+        // clipped at the top left corner
+        let clipped = positions(painter.fill_circle(Coordinate::new(0, 0), 2, (1, 2, 3)));
+        assert_eq!(
+            clipped,
+            [
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (0, 2),
+                (1, 2)
+            ]
+        );
+
+        // only the visible rows of huge circles are calculated
+        let small = Painter::new((10, 10));
+        assert_eq!(
+            small
+                .fill_circle(Coordinate::new(5, 5), u32::MAX, (1, 2, 3))
+                .count(),
+            100
+        );
+        let edge =
+            positions(small.fill_circle(Coordinate::new(5, 1_000_004), 1_000_000, (1, 2, 3)));
+        // the top row of the circle is y = 4
+        assert_eq!(edge.len(), 60);
+        assert!(edge.iter().all(|&(_, y)| y >= 4));
+    }
+
+    #[test]
+    fn polygons() {
+        let painter = Painter::new((100, 100));
+        let triangle = [
+            Coordinate::new(10, 10),
+            Coordinate::new(20, 10),
+            Coordinate::new(10, 20),
+        ];
+        let outline = positions(painter.polygon(&triangle, (1, 2, 3)));
+        assert_unique(&outline);
+        assert_eq!(outline.len(), 30);
+        assert!(outline.contains(&(10, 10)) && outline.contains(&(20, 10)));
+
+        // edges meeting at an acute angle overlap
+        let acute = [
+            Coordinate::new(0, 0),
+            Coordinate::new(10, 1),
+            Coordinate::new(0, 2),
+        ];
+        let outline = positions(painter.polygon(&acute, (1, 2, 3)));
+        assert_unique(&outline);
+        assert_eq!(outline.len(), 18);
+
+        let filled = positions(painter.fill_polygon(&triangle, (1, 2, 3)));
+        assert_unique(&filled);
+        assert_eq!(filled.len(), 55);
+        assert!(filled.contains(&(10, 19)) && !filled.contains(&(10, 20)));
+
+        // two triangles sharing an edge do not overlap
+        let other = [
+            Coordinate::new(20, 10),
+            Coordinate::new(20, 20),
+            Coordinate::new(10, 20),
+        ];
+        let mut both = filled.clone();
+        both.extend(positions(painter.fill_polygon(&other, (1, 2, 3))));
+        both.sort_unstable_by_key(|&(x, y)| (y, x));
+        assert_unique(&both);
+        assert_eq!(both.len(), 100);
+
+        assert_eq!(
+            positions(painter.polygon(&[Coordinate::new(1, 2)], (1, 2, 3))),
+            [(1, 2)]
+        );
+        assert_eq!(painter.polygon(&[], (1, 2, 3)).count(), 0);
+        assert_eq!(painter.fill_polygon(&[], (1, 2, 3)).count(), 0);
+
+        // clipped to the canvas
+        let large = [
+            Coordinate::new(0, 0),
+            Coordinate::new(1000, 0),
+            Coordinate::new(1000, 1000),
+            Coordinate::new(0, 1000),
+        ];
+        assert_eq!(painter.fill_polygon(&large, (1, 2, 3)).count(), 10_000);
+    }
+
+    #[test]
+    fn flood_fill() {
+        let canvas = Canvas::new(10, 10);
+        let painter = Painter::new(canvas.dimensions());
+        let white = Color::rgb(255, 255, 255);
+        for pixel in painter.line(Coordinate::new(0, 5), Coordinate::new(9, 5), white) {
+            canvas.set(&pixel);
+        }
+
+        let fill: Vec<Pixel> = painter
+            .flood_fill(Coordinate::new(3, 3), (255, 0, 0), |position| {
+                canvas.get(position)
+            })
+            .collect();
+        assert_eq!(fill.len(), 50);
+        for pixel in &fill {
+            assert!(pixel.position.y < 5);
+            canvas.set(pixel);
+        }
+
+        // the line is a single area
+        assert_eq!(
+            painter
+                .flood_fill(Coordinate::new(9, 5), white, |position| canvas
+                    .get(position))
+                .count(),
+            10
+        );
+        // only inside of the clip region
+        let clipped = Painter::with_clip(Rect::new(0, 6, 3, 3));
+        assert_eq!(
+            clipped
+                .flood_fill(Coordinate::new(0, 6), white, |position| canvas
+                    .get(position))
+                .count(),
+            9
+        );
+        assert_eq!(
+            clipped
+                .flood_fill(Coordinate::new(0, 0), white, |position| canvas
+                    .get(position))
+                .count(),
+            0
+        );
+    }
+}
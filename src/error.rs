@@ -1,9 +1,10 @@
-use std::convert::From;
+use core::convert::From;
+use core::fmt;
+use core::num::ParseIntError;
+use core::result;
+use core::str::Utf8Error;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
-use std::num::ParseIntError;
-use std::result;
-use std::str::Utf8Error;
 
 /// Pixelflut [`Result`] alias
 pub type PixelflutResult<T> = result::Result<T, PixelflutError>;
@@ -23,6 +24,7 @@ impl fmt::Debug for PixelflutError {
 
 #[derive(Debug)]
 enum Repr {
+    #[cfg(feature = "std")]
     Io(std::io::Error),
     ParseInt(ParseIntError),
     Utf8(Utf8Error),
@@ -72,6 +74,7 @@ impl PixelflutError {
     /// Returns the corresponding `ErrorKind` for this error.
     pub fn kind(&self) -> PixelflutErrorKind {
         match self.repr {
+            #[cfg(feature = "std")]
             Repr::Io(_) => PixelflutErrorKind::Io,
             Repr::ParseInt(_) => PixelflutErrorKind::Parse,
             Repr::Utf8(_) => PixelflutErrorKind::Parse,
@@ -84,6 +87,7 @@ impl PixelflutError {
 impl fmt::Display for PixelflutError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self.repr {
+            #[cfg(feature = "std")]
             Repr::Io(ref err) => write!(fmt, "io error: {}", err),
             Repr::ParseInt(ref err) => write!(fmt, "parse int error: {}", err),
             Repr::Utf8(err) => write!(fmt, "utf8 error: {}", err),
@@ -95,6 +99,7 @@ impl fmt::Display for PixelflutError {
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for PixelflutError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self.repr {
@@ -107,6 +112,7 @@ impl error::Error for PixelflutError {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for PixelflutError {
     fn from(err: std::io::Error) -> PixelflutError {
         PixelflutError {
@@ -0,0 +1,167 @@
+//! A module deciding how server streams react to invalid lines.
+
+/// Decides how a server stream reacts to invalid lines.
+///
+/// Every invalid line is answered with an `ERROR`.
+/// With the default policy, the error is returned on the first invalid line,
+/// which usually closes the connection.
+/// With [`skip`](Self::skip), invalid lines are skipped
+/// until one of the configured limits is exceeded.
+///
+/// Errors while reading from the stream, like lines that are to long,
+/// are always returned.
+///
+/// # Examples
+///
+/// ```
+/// use pixelflut::ErrorPolicy;
+///
+/// // Skip invalid lines, but disconnect after 100 errors
+/// // or if more than 10% of at least 1000 lines were invalid.
+/// let policy = ErrorPolicy::skip()
+///     .with_max_errors(100)
+///     .with_max_error_rate(0.1, 1000);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ErrorPolicy {
+    max_errors: Option<u64>,
+    max_error_rate: Option<(f64, u64)>,
+}
+
+impl ErrorPolicy {
+    /// Returns the error on the first invalid line.
+    pub const fn fail_fast() -> ErrorPolicy {
+        ErrorPolicy {
+            max_errors: Some(0),
+            max_error_rate: None,
+        }
+    }
+
+    /// Skips all invalid lines without a limit.
+    pub const fn skip() -> ErrorPolicy {
+        ErrorPolicy {
+            max_errors: None,
+            max_error_rate: None,
+        }
+    }
+
+    /// Returns the error, if more than `max_errors` invalid lines were received.
+    pub const fn with_max_errors(self, max_errors: u64) -> ErrorPolicy {
+        ErrorPolicy {
+            max_errors: Some(max_errors),
+            ..self
+        }
+    }
+
+    /// Returns the error, if the part of invalid lines is larger than `max_error_rate`.
+    ///
+    /// The rate is only checked after at least `min_lines` lines were received,
+    /// so a single typo at the start of a connection is not fatal.
+    pub fn with_max_error_rate(self, max_error_rate: f64, min_lines: u64) -> ErrorPolicy {
+        ErrorPolicy {
+            max_error_rate: Some((max_error_rate, min_lines)),
+            ..self
+        }
+    }
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> ErrorPolicy {
+        ErrorPolicy::fail_fast()
+    }
+}
+
+/// Counts the valid and invalid lines of a connection.
+#[derive(Copy, Clone, Debug, Default)]
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) struct ErrorCounter {
+    lines: u64,
+    errors: u64,
+}
+
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+impl ErrorCounter {
+    /// Returns the number of invalid lines.
+    pub(crate) fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Records a received line.
+    pub(crate) fn record_line(&mut self) {
+        self.lines += 1;
+    }
+
+    /// Records that the last received line was invalid.
+    ///
+    /// Returns `true`, if the policy limits are exceeded and the error should be returned.
+    pub(crate) fn record_error(&mut self, policy: &ErrorPolicy) -> bool {
+        self.errors += 1;
+        if let Some(max_errors) = policy.max_errors {
+            if self.errors > max_errors {
+                return true;
+            }
+        }
+        if let Some((max_error_rate, min_lines)) = policy.max_error_rate {
+            if self.lines >= min_lines && self.errors as f64 / self.lines as f64 > max_error_rate {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error_policy::ErrorCounter;
+    use crate::ErrorPolicy;
+
+    #[test]
+    fn fail_fast() {
+        let mut counter = ErrorCounter::default();
+        counter.record_line();
+        assert!(counter.record_error(&ErrorPolicy::default()));
+    }
+
+    /// Records an invalid line.
+    fn record_invalid(counter: &mut ErrorCounter, policy: &ErrorPolicy) -> bool {
+        counter.record_line();
+        counter.record_error(policy)
+    }
+
+    #[test]
+    fn max_errors() {
+        let policy = ErrorPolicy::skip().with_max_errors(2);
+        let mut counter = ErrorCounter::default();
+        assert!(!record_invalid(&mut counter, &policy));
+        assert!(!record_invalid(&mut counter, &policy));
+        assert!(record_invalid(&mut counter, &policy));
+        assert_eq!(counter.errors(), 3);
+    }
+
+    #[test]
+    fn max_error_rate() {
+        let policy = ErrorPolicy::skip().with_max_error_rate(0.5, 4);
+        let mut counter = ErrorCounter::default();
+        // the rate is not checked before 4 lines
+        assert!(!record_invalid(&mut counter, &policy));
+        assert!(!record_invalid(&mut counter, &policy));
+        counter.record_line();
+        counter.record_line();
+        // 3 of 5 lines are invalid
+        assert!(record_invalid(&mut counter, &policy));
+
+        let mut counter = ErrorCounter::default();
+        for _ in 0..10 {
+            counter.record_line();
+        }
+        assert!(!record_invalid(&mut counter, &policy));
+    }
+
+    #[test]
+    fn skip_without_limits() {
+        let mut counter = ErrorCounter::default();
+        for _ in 0..1000 {
+            assert!(!record_invalid(&mut counter, &ErrorPolicy::skip()));
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Parser for fonts in the Glyph Bitmap Distribution Format (BDF).
+use super::{Font, Glyph};
+use crate::error::{PixelflutErrorKind, PixelflutResult};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Bounding box of a glyph as `(width, height, x offset, y offset)`,
+/// with the offsets relative to the origin on the baseline.
+type BoundingBox = (u32, u32, i32, i32);
+
+/// A glyph that is currently parsed.
+#[derive(Default)]
+struct PartialGlyph {
+    encoding: Option<char>,
+    advance: Option<u32>,
+    bounding_box: Option<BoundingBox>,
+    bitmap: Vec<u8>,
+}
+
+pub(super) fn parse(data: &[u8]) -> PixelflutResult<Font> {
+    let mut lines = data
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+
+    match lines.next().map(split_keyword) {
+        Some((b"STARTFONT", _)) => {}
+        _ => return Err(PixelflutErrorKind::Parse.with_description("not a BDF font")),
+    }
+
+    let mut font_bounding_box = None;
+    let mut ascent = None;
+    let mut descent = None;
+    let mut glyphs = Vec::new();
+    let mut glyph: Option<PartialGlyph> = None;
+    let mut complete = false;
+
+    while let Some(line) = lines.next() {
+        let (keyword, args) = split_keyword(line);
+        match (keyword, glyph.as_mut()) {
+            (b"FONTBOUNDINGBOX", None) => font_bounding_box = Some(bounding_box(args)?),
+            (b"FONT_ASCENT", None) => ascent = Some(numbers::<1>(args)?[0]),
+            (b"FONT_DESCENT", None) => descent = Some(numbers::<1>(args)?[0]),
+            (b"STARTCHAR", None) => glyph = Some(PartialGlyph::default()),
+            (b"ENCODING", Some(glyph)) => {
+                // negative encodings are glyphs without a standard encoding
+                glyph.encoding = u32::try_from(numbers::<1>(args)?[0])
+                    .ok()
+                    .and_then(char::from_u32);
+            }
+            (b"DWIDTH", Some(glyph)) => {
+                glyph.advance = Some(u32::try_from(numbers::<1>(args)?[0]).unwrap_or(0));
+            }
+            (b"BBX", Some(glyph)) => glyph.bounding_box = Some(bounding_box(args)?),
+            (b"BITMAP", Some(glyph)) => {
+                let (width, height, _, _) = glyph
+                    .bounding_box
+                    .or(font_bounding_box)
+                    .ok_or_else(missing_bounding_box)?;
+                let row_len = (width as usize).div_ceil(8);
+                // every byte of the bitmap is written as two hex digits in the data
+                if row_len.saturating_mul(height as usize) > data.len() / 2 {
+                    return Err(PixelflutErrorKind::Parse
+                        .with_description("bitmap of a glyph is too large"));
+                }
+                for _ in 0..height {
+                    let row = lines.next().ok_or_else(|| {
+                        PixelflutErrorKind::Parse.with_description("bitmap of a glyph is too short")
+                    })?;
+                    for i in 0..row_len {
+                        // rows may be padded with more bytes than needed
+                        let byte = row.get(2 * i..2 * i + 2).unwrap_or(b"00");
+                        let byte = core::str::from_utf8(byte)?;
+                        glyph.bitmap.push(u8::from_str_radix(byte, 16)?);
+                    }
+                }
+            }
+            (b"ENDCHAR", Some(_)) => {
+                if let Some(glyph) = glyph.take() {
+                    glyphs.push(glyph);
+                }
+            }
+            (b"ENDFONT", None) => {
+                complete = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+    if !complete {
+        return Err(PixelflutErrorKind::Parse.with_description("BDF font is truncated"));
+    }
+
+    // sizes of bounding boxes are parsed from an `i32`, so they fit into an `i32` again
+    let (_, font_height, _, font_y) = font_bounding_box.unwrap_or_default();
+    let ascent = match ascent {
+        Some(ascent) => ascent,
+        None => (font_height as i32)
+            .checked_add(font_y)
+            .ok_or_else(overflow)?,
+    };
+    let descent = match descent {
+        Some(descent) => descent,
+        None => font_y.checked_neg().ok_or_else(overflow)?,
+    };
+    let mut font = Font {
+        line_height: u32::try_from(ascent.checked_add(descent).ok_or_else(overflow)?).unwrap_or(0),
+        glyphs: Vec::new(),
+        chars: BTreeMap::new(),
+    };
+    for glyph in glyphs {
+        let c = match glyph.encoding {
+            Some(c) => c,
+            None => continue,
+        };
+        let (width, height, x, y) = glyph
+            .bounding_box
+            .or(font_bounding_box)
+            .ok_or_else(missing_bounding_box)?;
+        // the bounding boxes may follow the bitmap, so the size is checked once all are known
+        let bitmap_len = (width as usize).div_ceil(8).checked_mul(height as usize);
+        if bitmap_len != Some(glyph.bitmap.len()) {
+            return Err(PixelflutErrorKind::Parse
+                .with_description("bitmap does not match the bounding box"));
+        }
+        // the offset is from the baseline to the bottom of the bitmap
+        let top = (height as i32)
+            .checked_add(y)
+            .and_then(|bottom| ascent.checked_sub(bottom))
+            .ok_or_else(overflow)?;
+        font.chars.insert(c, font.glyphs.len());
+        font.glyphs.push(Glyph {
+            advance: glyph.advance.unwrap_or(width),
+            width,
+            height,
+            left: x,
+            top,
+            bitmap: glyph.bitmap,
+        });
+    }
+    Ok(font)
+}
+
+/// Splits a line into the keyword and its arguments.
+fn split_keyword(line: &[u8]) -> (&[u8], &[u8]) {
+    match memchr::memchr(b' ', line) {
+        Some(pos) => (&line[..pos], &line[pos + 1..]),
+        None => (line, &[]),
+    }
+}
+
+/// Parses the first `N` numbers of the arguments.
+fn numbers<const N: usize>(args: &[u8]) -> PixelflutResult<[i32; N]> {
+    let mut args = core::str::from_utf8(args)?.split_whitespace();
+    let mut numbers = [0; N];
+    for number in numbers.iter_mut() {
+        *number = args
+            .next()
+            .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
+            .parse()?;
+    }
+    Ok(numbers)
+}
+
+fn bounding_box(args: &[u8]) -> PixelflutResult<BoundingBox> {
+    let [width, height, x, y] = numbers::<4>(args)?;
+    match (u32::try_from(width), u32::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height, x, y)),
+        _ => Err(PixelflutErrorKind::Parse.with_description("negative size of a bounding box")),
+    }
+}
+
+fn missing_bounding_box() -> crate::PixelflutError {
+    PixelflutErrorKind::Parse.with_description("glyph without a bounding box")
+}
+
+fn overflow() -> crate::PixelflutError {
+    PixelflutErrorKind::Parse.with_description("font metrics are out of range")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Font, PixelflutErrorKind, Text};
+    use alloc::vec::Vec;
+
+    const FONT: &[u8] = b"STARTFONT 2.1\r
+FONT -test-fixed-medium-r-normal--6-60-75-75-c-40-iso10646-1\r
+SIZE 6 75 75\r
+FONTBOUNDINGBOX 4 6 0 -1\r
+STARTPROPERTIES 3\r
+COPYRIGHT \"\xa9 nobody\"\r
+FONT_ASCENT 5\r
+FONT_DESCENT 1\r
+ENDPROPERTIES\r
+CHARS 3\r
+STARTCHAR A\r
+ENCODING 65\r
+SWIDTH 666 0\r
+DWIDTH 4 0\r
+BBX 3 5 0 0\r
+BITMAP\r
+40\r
+A0\r
+E0\r
+A0\r
+A0\r
+ENDCHAR\r
+STARTCHAR j\r
+ENCODING 106\r
+DWIDTH 3 0\r
+BBX 3 5 -1 -1\r
+BITMAP\r
+2000\r
+00\r
+20\r
+20\r
+C0\r
+ENDCHAR\r
+STARTCHAR unencoded\r
+ENCODING -1\r
+DWIDTH 4 0\r
+BBX 1 1 0 0\r
+BITMAP\r
+80\r
+ENDCHAR\r
+ENDFONT\r
+";
+
+    #[test]
+    fn parse() {
+        let font = Font::from_bdf(FONT).unwrap();
+        assert_eq!(font.line_height(), 6);
+        assert!(font.contains('A') && font.contains('j'));
+        assert_eq!(font.glyphs.len(), 2);
+        assert_eq!(font.text_size("Aj"), (7, 6));
+
+        let a = &font.glyphs[font.chars[&'A']];
+        assert_eq!((a.left, a.top), (0, 0));
+        assert!(a.is_set(1, 0) && a.is_set(0, 2) && !a.is_set(1, 3));
+
+        // the descender of the `j` reaches below the baseline and left of the pen
+        let positions: Vec<(u32, u32)> = Text::new(&font, "j")
+            .with_position((1, 0))
+            .pixels()
+            .map(|pixel| pixel.position.into())
+            .collect();
+        assert_eq!(positions, [(2, 1), (2, 3), (2, 4), (0, 5), (1, 5)]);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Font::from_bdf(b"").is_err());
+        assert!(Font::from_bdf(b"STARTCHAR A\nENDFONT").is_err());
+        assert!(Font::from_bdf(b"STARTFONT 2.1\n").is_err());
+        assert_eq!(
+            Font::from_bdf(b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nBBX 8 2 0 0\nBITMAP\nFF\n")
+                .unwrap_err()
+                .kind(),
+            PixelflutErrorKind::Parse
+        );
+        assert_eq!(
+            Font::from_bdf(b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nBITMAP\nFF\nENDCHAR\n")
+                .unwrap_err()
+                .kind(),
+            PixelflutErrorKind::Parse
+        );
+        assert!(Font::from_bdf(b"STARTFONT 2.1\nFONTBOUNDINGBOX 1 2\n").is_err());
+
+        // bitmaps that do not match the bounding box
+        for font in [
+            &b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nBBX 8 8 0 0\nENDCHAR\nENDFONT\n"[..],
+            b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nBBX 8 1 0 0\nBITMAP\nFF\nBBX 8 8 0 0\nENDCHAR\nENDFONT\n",
+            b"STARTFONT 2.1\nFONTBOUNDINGBOX 8 2 0 0\nSTARTCHAR A\nENCODING 65\nENDCHAR\nENDFONT\n",
+            b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nENDCHAR\nFONTBOUNDINGBOX 8 8 0 0\nENDFONT\n",
+            b"STARTFONT 2.1\nSTARTCHAR A\nENCODING 65\nBBX 2147483647 1 0 0\nBITMAP\nFF\nENDCHAR\nENDFONT\n",
+        ] {
+            assert_eq!(
+                Font::from_bdf(font).unwrap_err().kind(),
+                PixelflutErrorKind::Parse
+            );
+        }
+
+        // metrics overflowing an `i32`
+        for font in [
+            &b"STARTFONT 2.1\nFONT_ASCENT 2147483647\nFONT_DESCENT 1\nENDFONT\n"[..],
+            b"STARTFONT 2.1\nFONTBOUNDINGBOX 1 2147483647 0 1\nENDFONT\n",
+            b"STARTFONT 2.1\nFONTBOUNDINGBOX 1 1 0 -2147483648\nENDFONT\n",
+            b"STARTFONT 2.1\nFONT_ASCENT -2147483648\nFONT_DESCENT 0\nSTARTCHAR A\nENCODING 65\nBBX 0 1 0 0\nENDCHAR\nENDFONT\n",
+        ] {
+            assert_eq!(
+                Font::from_bdf(font).unwrap_err().kind(),
+                PixelflutErrorKind::Parse
+            );
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Bitmap fonts and text rendering.
+use crate::{Color, Coordinate, Pixel, PixelflutResult, Rect};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::{max, min};
+use core::convert::TryFrom;
+
+mod bdf;
+mod builtin;
+mod psf;
+
+/// A single character of a bitmap font.
+#[derive(Clone, Debug)]
+struct Glyph {
+    /// Distance from this character to the next one.
+    advance: u32,
+    width: u32,
+    height: u32,
+    /// Horizontal offset of the bitmap from the pen position.
+    left: i32,
+    /// Vertical offset of the bitmap from the top of the line.
+    top: i32,
+    /// Rows of the bitmap, each padded to full bytes, with the leftmost pixel in the highest bit.
+    bitmap: Vec<u8>,
+}
+
+impl Glyph {
+    /// Returns `true`, if the pixel of the bitmap is set.
+    fn is_set(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return false;
+        }
+        let row_len = (self.width as usize).div_ceil(8);
+        let byte = self.bitmap[y as usize * row_len + x as usize / 8];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+}
+
+/// Bitmap font used to render [`Text`].
+///
+/// A built-in font covers the printable ASCII characters.
+/// Other fonts can be loaded from BDF and PSF files.
+/// Characters that are missing in the font are drawn as `?`, if the font contains one.
+#[derive(Clone, Debug)]
+pub struct Font {
+    line_height: u32,
+    glyphs: Vec<Glyph>,
+    /// Index into `glyphs` for every character.
+    chars: BTreeMap<char, usize>,
+}
+
+impl Font {
+    /// Returns the built-in font with 5x7 pixel glyphs on a 6x8 grid.
+    ///
+    /// It covers the printable ASCII characters.
+    pub fn builtin() -> Font {
+        builtin::font()
+    }
+
+    /// Loads a font in the Glyph Bitmap Distribution Format (BDF).
+    ///
+    /// Glyphs without an encoding are skipped.
+    pub fn from_bdf(data: &[u8]) -> PixelflutResult<Font> {
+        bdf::parse(data)
+    }
+
+    /// Loads a PC Screen Font (PSF), version 1 or 2.
+    ///
+    /// Without a unicode table, glyph `n` is used for the character `n`.
+    pub fn from_psf(data: &[u8]) -> PixelflutResult<Font> {
+        psf::parse(data)
+    }
+
+    /// Returns the height of a line of text.
+    pub fn line_height(&self) -> u32 {
+        self.line_height
+    }
+
+    /// Returns `true`, if the font has a glyph for the character.
+    pub fn contains(&self, c: char) -> bool {
+        self.chars.contains_key(&c)
+    }
+
+    /// Returns the size of the text without scaling as `(width, height)`.
+    ///
+    /// The width is the width of the longest line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Font;
+    /// let font = Font::builtin();
+    /// assert_eq!(font.text_size("Hello"), (30, 8));
+    /// assert_eq!(font.text_size("Hello\nWorld!"), (36, 16));
+    /// ```
+    pub fn text_size(&self, text: &str) -> (u32, u32) {
+        let mut width = 0;
+        let mut lines = 0;
+        for line in text.lines() {
+            let line_width = line
+                .chars()
+                .filter_map(|c| self.glyph(c))
+                .map(|glyph| glyph.advance)
+                .fold(0u32, u32::saturating_add);
+            width = max(width, line_width);
+            lines += 1;
+        }
+        (width, self.line_height.saturating_mul(lines))
+    }
+
+    /// Returns the glyph for a character or the replacement glyph.
+    fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.chars
+            .get(&c)
+            .or_else(|| self.chars.get(&'?'))
+            .map(|&index| &self.glyphs[index])
+    }
+}
+
+/// Text rendered with a bitmap [`Font`].
+///
+/// The text is rendered to [`Pixel`]s, which can be passed to the `set_pixels` method
+/// of the clients or to a [`PixelBuffer`](crate::PixelBuffer).
+/// Lines are separated by `\n`.
+///
+/// # Examples
+///
+/// ```
+/// use pixelflut::{Font, PixelBuffer, Text};
+///
+/// let font = Font::builtin();
+/// let text = Text::new(&font, "Team Rocket")
+///     .with_position((10, 20))
+///     .with_scale(2)
+///     .with_color((255, 255, 255))
+///     .with_background((0, 0, 0));
+/// let buffer: PixelBuffer = text.pixels().collect();
+/// assert_eq!(text.bounds().area(), 12 * 11 * 16);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Text<'a> {
+    font: &'a Font,
+    text: &'a str,
+    position: Coordinate,
+    scale: u32,
+    color: Color,
+    background: Option<Color>,
+}
+
+impl<'a> Text<'a> {
+    /// Creates white text without background at `(0, 0)`.
+    pub fn new(font: &'a Font, text: &'a str) -> Text<'a> {
+        Text {
+            font,
+            text,
+            position: Coordinate::new(0, 0),
+            scale: 1,
+            color: Color::rgb(255, 255, 255),
+            background: None,
+        }
+    }
+
+    /// Sets the top left corner of the text.
+    pub fn with_position(self, position: impl Into<Coordinate>) -> Text<'a> {
+        Text {
+            position: position.into(),
+            ..self
+        }
+    }
+
+    /// Sets how many pixels are drawn for every pixel of the font in each direction.
+    ///
+    /// A scale of 0 is treated like 1.
+    pub fn with_scale(self, scale: u32) -> Text<'a> {
+        Text {
+            scale: max(scale, 1),
+            ..self
+        }
+    }
+
+    /// Sets the color of the characters.
+    pub fn with_color(self, color: impl Into<Color>) -> Text<'a> {
+        Text {
+            color: color.into(),
+            ..self
+        }
+    }
+
+    /// Sets the color drawn behind the characters.
+    ///
+    /// Without a background, only the pixels of the characters are drawn.
+    pub fn with_background(self, background: impl Into<Color>) -> Text<'a> {
+        Text {
+            background: Some(background.into()),
+            ..self
+        }
+    }
+
+    /// Returns the region covered by the text.
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = self.font.text_size(self.text);
+        Rect::new(
+            self.position.x,
+            self.position.y,
+            width.saturating_mul(self.scale),
+            height.saturating_mul(self.scale),
+        )
+    }
+
+    /// Returns the pixels of the text.
+    ///
+    /// Every pixel is only returned once.
+    /// Pixels outside of the coordinate range are skipped.
+    pub fn pixels(&self) -> impl Iterator<Item = Pixel> + Clone + 'a {
+        let font = self.font;
+        // large metrics times a large scale saturate, as they are outside of the coordinates anyway
+        let scale = i64::from(self.scale);
+        let scaled = move |value: i64| value.saturating_mul(scale);
+        let line_height = scaled(i64::from(font.line_height));
+
+        // the position of every character
+        let mut characters = Vec::new();
+        let mut y = i64::from(self.position.y);
+        for line in self.text.lines() {
+            let mut x = i64::from(self.position.x);
+            for glyph in line.chars().filter_map(|c| font.glyph(c)) {
+                characters.push((x, y, glyph));
+                x = x.saturating_add(scaled(i64::from(glyph.advance)));
+            }
+            y = y.saturating_add(line_height);
+        }
+
+        // `x` and `y` are not negative, so the cells can be limited to the coordinate range
+        let end = i64::from(u32::MAX) + 1;
+        let (color, background) = (self.color, self.background);
+        characters.into_iter().flat_map(move |(x, y, glyph)| {
+            // the cell covers the advance, the line and the bitmap
+            let left = max(scaled(i64::from(min(glyph.left, 0))), -x);
+            let right = min(
+                scaled(max(
+                    i64::from(glyph.advance),
+                    i64::from(glyph.left) + i64::from(glyph.width),
+                )),
+                end - x,
+            );
+            let top = max(scaled(i64::from(min(glyph.top, 0))), -y);
+            let bottom = min(
+                scaled(max(
+                    i64::from(font.line_height),
+                    i64::from(glyph.top) + i64::from(glyph.height),
+                )),
+                end - y,
+            );
+            let advance = scaled(i64::from(glyph.advance));
+
+            (top..bottom).flat_map(move |dy| {
+                (left..right).filter_map(move |dx| {
+                    let color = if glyph.is_set(
+                        dx.div_euclid(scale) - i64::from(glyph.left),
+                        dy.div_euclid(scale) - i64::from(glyph.top),
+                    ) {
+                        color
+                    } else if (0..advance).contains(&dx) && (0..line_height).contains(&dy) {
+                        background?
+                    } else {
+                        return None;
+                    };
+                    let position =
+                        Coordinate::new(u32::try_from(x + dx).ok()?, u32::try_from(y + dy).ok()?);
+                    Some(Pixel::new(position, color))
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Font, Text};
+    use crate::{Color, Painter, Pixel, Rect};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    /// Draws the pixels as rows of `#` for the color and `.` for everything else.
+    fn render(pixels: impl Iterator<Item = Pixel>, bounds: Rect) -> Vec<String> {
+        let mut rows = vec![vec!['.'; bounds.width() as usize]; bounds.height() as usize];
+        for pixel in pixels {
+            let x = (pixel.position.x - bounds.origin().x) as usize;
+            let y = (pixel.position.y - bounds.origin().y) as usize;
+            assert_eq!(rows[y][x], '.', "{:?} drawn twice", pixel.position);
+            rows[y][x] = if pixel.color == Color::rgb(255, 255, 255) {
+                '#'
+            } else {
+                '-'
+            };
+        }
+        rows.into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn builtin() {
+        let font = Font::builtin();
+        assert_eq!(font.line_height(), 8);
+        assert!((' '..='~').all(|c| font.contains(c)));
+        assert!(!font.contains('ä'));
+
+        let text = Text::new(&font, "Hi!").with_position((3, 4));
+        assert_eq!(text.bounds(), Rect::new(3, 4, 18, 8));
+        assert_eq!(
+            render(text.pixels(), text.bounds()),
+            [
+                "#...#...#.....#...",
+                "#...#.........#...",
+                "#...#..##.....#...",
+                "#####...#.....#...",
+                "#...#...#.....#...",
+                "#...#...#.........",
+                "#...#..###....#...",
+                "..................",
+            ]
+        );
+    }
+
+    #[test]
+    fn scale_and_background() {
+        let font = Font::builtin();
+        let text = Text::new(&font, "-\n.")
+            .with_scale(2)
+            .with_background((0, 0, 0));
+        assert_eq!(text.bounds(), Rect::new(0, 0, 12, 32));
+        let rows = render(text.pixels(), text.bounds());
+        assert!(rows.iter().all(|row| !row.contains('.')));
+        assert_eq!(rows[6], "##########--");
+        assert_eq!(rows[7], "##########--");
+        assert_eq!(rows[26], "--####------");
+        assert_eq!(text.pixels().count() as u64, text.bounds().area());
+    }
+
+    #[test]
+    fn missing_characters() {
+        let font = Font::builtin();
+        assert_eq!(font.text_size("ä"), font.text_size("?"));
+        let question: Vec<Pixel> = Text::new(&font, "?").pixels().collect();
+        assert_eq!(Text::new(&font, "ä").pixels().collect::<Vec<_>>(), question);
+        assert_eq!(font.text_size(""), (0, 0));
+    }
+
+    #[test]
+    fn huge_metrics() {
+        let font = Font::from_bdf(
+            b"STARTFONT 2.1
+FONT_ASCENT 2147483647
+FONT_DESCENT 0
+STARTCHAR A
+ENCODING 65
+DWIDTH 2147483647 0
+BBX 1 1 0 2147483646
+BITMAP
+80
+ENDCHAR
+ENDFONT
+",
+        )
+        .unwrap();
+        // the positions of the following characters saturate instead of overflowing
+        let text = Text::new(&font, "AA\nA\nA").with_scale(u32::MAX);
+        let first: Vec<(u32, u32)> = text
+            .pixels()
+            .take(2)
+            .map(|pixel| pixel.position.into())
+            .collect();
+        assert_eq!(first, [(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn clipped() {
+        let font = Font::builtin();
+        let painter = Painter::new((10, 10));
+        let text = Text::new(&font, "MMM").with_position((5, 5));
+        assert!(painter
+            .text(&text)
+            .all(|pixel| pixel.position.x < 10 && pixel.position.y < 10));
+        assert_eq!(painter.text(&text).count(), 14);
+    }
+}
@@ -0,0 +1,238 @@
+//! Parser for PC Screen Fonts (PSF), as used by the Linux console.
+use super::{Font, Glyph};
+use crate::error::{PixelflutError, PixelflutErrorKind, PixelflutResult};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_MODE_HAS_SEQUENCES: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_START_SEQUENCE: u16 = 0xfffe;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+pub(super) fn parse(data: &[u8]) -> PixelflutResult<Font> {
+    if data.starts_with(&PSF1_MAGIC) {
+        parse_psf1(data)
+    } else if data.starts_with(&PSF2_MAGIC) {
+        parse_psf2(data)
+    } else {
+        Err(PixelflutErrorKind::Parse.with_description("not a PSF font"))
+    }
+}
+
+fn parse_psf1(data: &[u8]) -> PixelflutResult<Font> {
+    let mode = *data.get(2).ok_or_else(truncated)?;
+    let height = u32::from(*data.get(3).ok_or_else(truncated)?);
+    if height == 0 {
+        return Err(PixelflutErrorKind::Parse.with_description("glyph size does not fit"));
+    }
+    let length = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+    let glyphs_len = length * height as usize;
+    let glyphs = data.get(4..4 + glyphs_len).ok_or_else(truncated)?;
+    let mut font = font_from_bitmaps(glyphs.chunks_exact(height as usize), 8, height);
+
+    if mode & (PSF1_MODE_HAS_TABLE | PSF1_MODE_HAS_SEQUENCES) != 0 {
+        let table = &data[4 + glyphs_len..];
+        let mut entries = table
+            .chunks_exact(2)
+            .map(|entry| u16::from_le_bytes([entry[0], entry[1]]));
+        for index in 0..length {
+            // single characters come first, sequences of combined characters are skipped
+            let mut in_sequence = false;
+            loop {
+                match entries.next().ok_or_else(truncated)? {
+                    PSF1_SEPARATOR => break,
+                    PSF1_START_SEQUENCE => in_sequence = true,
+                    c if !in_sequence => {
+                        if let Some(c) = char::from_u32(u32::from(c)) {
+                            font.chars.insert(c, index);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    } else {
+        map_indices(&mut font);
+    }
+    Ok(font)
+}
+
+fn parse_psf2(data: &[u8]) -> PixelflutResult<Font> {
+    let header = |index: usize| -> PixelflutResult<u32> {
+        let bytes = data.get(4 * index..4 * index + 4).ok_or_else(truncated)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let header_len = header(2)? as usize;
+    let flags = header(3)?;
+    let length = header(4)? as usize;
+    let glyph_len = header(5)? as usize;
+    let height = header(6)?;
+    let width = header(7)?;
+    let bitmap_len = (width as usize)
+        .div_ceil(8)
+        .checked_mul(height as usize)
+        .ok_or_else(|| PixelflutErrorKind::Parse.with_description("glyph size does not fit"))?;
+    if glyph_len == 0 || glyph_len < bitmap_len {
+        return Err(PixelflutErrorKind::Parse.with_description("glyph size does not fit"));
+    }
+
+    let glyphs_len = length.checked_mul(glyph_len).ok_or_else(truncated)?;
+    let glyphs = data
+        .get(header_len..header_len.saturating_add(glyphs_len))
+        .ok_or_else(truncated)?;
+    let mut font = font_from_bitmaps(glyphs.chunks_exact(glyph_len), width, height);
+
+    if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+        let mut table = &data[header_len + glyphs_len..];
+        for index in 0..length {
+            let end = memchr::memchr(PSF2_SEPARATOR, table).ok_or_else(truncated)?;
+            // single characters come first, sequences of combined characters are skipped
+            let chars = &table[..end];
+            let chars = match memchr::memchr(PSF2_START_SEQUENCE, chars) {
+                Some(pos) => &chars[..pos],
+                None => chars,
+            };
+            for c in core::str::from_utf8(chars)?.chars() {
+                font.chars.insert(c, index);
+            }
+            table = &table[end + 1..];
+        }
+    } else {
+        map_indices(&mut font);
+    }
+    Ok(font)
+}
+
+/// Creates a font with fixed size glyphs, but without any characters.
+fn font_from_bitmaps<'a>(bitmaps: impl Iterator<Item = &'a [u8]>, width: u32, height: u32) -> Font {
+    let bitmap_len = (width as usize).div_ceil(8) * height as usize;
+    Font {
+        line_height: height,
+        glyphs: bitmaps
+            .map(|bitmap| Glyph {
+                advance: width,
+                width,
+                height,
+                left: 0,
+                top: 0,
+                bitmap: bitmap[..bitmap_len].to_vec(),
+            })
+            .collect::<Vec<Glyph>>(),
+        chars: BTreeMap::new(),
+    }
+}
+
+/// Uses glyph `n` for the character `n`.
+fn map_indices(font: &mut Font) {
+    font.chars = (0..font.glyphs.len())
+        .filter_map(|index| Some((char::from_u32(index as u32)?, index)))
+        .collect();
+}
+
+fn truncated() -> PixelflutError {
+    PixelflutErrorKind::Parse.with_description("PSF font is truncated")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Font, PixelflutErrorKind};
+    use alloc::vec::Vec;
+
+    /// Glyphs with a 2 pixel high bitmap, where the first row is the glyph index.
+    fn bitmaps(length: usize, row_len: usize) -> Vec<u8> {
+        let mut bitmaps = Vec::new();
+        for index in 0..length {
+            bitmaps.extend(vec![index as u8; row_len]);
+            bitmaps.extend(vec![0; row_len]);
+        }
+        bitmaps
+    }
+
+    fn first_row(font: &Font, c: char) -> u8 {
+        font.glyph(c).unwrap().bitmap[0]
+    }
+
+    #[test]
+    fn psf1() {
+        let mut data = vec![0x36, 0x04, 0x00, 2];
+        data.extend(bitmaps(256, 1));
+        let font = Font::from_psf(&data).unwrap();
+        assert_eq!(font.line_height(), 2);
+        assert_eq!(font.text_size("ab"), (16, 2));
+        assert_eq!(first_row(&font, 'A'), b'A');
+        assert_eq!(first_row(&font, 'ÿ'), 0xff);
+        assert!(!font.contains('Ā'));
+
+        // with a unicode table
+        let mut data = vec![0x36, 0x04, 0x02, 2];
+        data.extend(bitmaps(256, 1));
+        for index in 0..256u16 {
+            let mut entries: Vec<u16> = match index {
+                1 => vec![0x2500, 0x2501, 0xfffe, 0x0041, 0x0301],
+                2 => vec![0x00c1],
+                _ => vec![],
+            };
+            entries.push(0xffff);
+            for entry in entries {
+                data.extend(entry.to_le_bytes().iter());
+            }
+        }
+        let font = Font::from_psf(&data).unwrap();
+        assert_eq!(first_row(&font, '─'), 1);
+        assert_eq!(first_row(&font, '━'), 1);
+        assert_eq!(first_row(&font, 'Á'), 2);
+        assert!(!font.contains('A'));
+
+        data.truncate(data.len() - 1);
+        assert!(Font::from_psf(&data).is_err());
+        assert!(Font::from_psf(&[0x36, 0x04, 0x00, 2, 0]).is_err());
+        assert_eq!(
+            Font::from_psf(&[0x36, 0x04, 0x00, 0]).unwrap_err().kind(),
+            PixelflutErrorKind::Parse
+        );
+    }
+
+    #[test]
+    fn psf2() {
+        let header = |flags: u32, length: u32| {
+            let mut header = vec![0x72, 0xb5, 0x4a, 0x86];
+            for field in &[0, 32, flags, length, 4, 2, 12] {
+                header.extend(u32::to_le_bytes(*field).iter());
+            }
+            header
+        };
+
+        let mut data = header(0, 128);
+        data.extend(bitmaps(128, 2));
+        let font = Font::from_psf(&data).unwrap();
+        assert_eq!(font.line_height(), 2);
+        assert_eq!(font.text_size("abc"), (36, 2));
+        assert_eq!(first_row(&font, 'z'), b'z');
+        assert!(!font.contains('\u{80}'));
+        // the second byte of a row is part of the 12 pixel wide glyph
+        assert!(font.glyph('\u{7f}').unwrap().is_set(11, 0));
+
+        let mut data = header(1, 3);
+        data.extend(bitmaps(3, 2));
+        // `b` and `c` share a glyph, `a` with a combining accent is a sequence
+        data.extend(b"a\xffbc\xfea\xcc\x81\xff\xff");
+        let font = Font::from_psf(&data).unwrap();
+        assert_eq!(first_row(&font, 'a'), 0);
+        assert_eq!(first_row(&font, 'b'), 1);
+        assert_eq!(first_row(&font, 'c'), 1);
+        assert!(!font.contains('\u{301}'));
+
+        data.truncate(data.len() - 1);
+        assert!(Font::from_psf(&data).is_err());
+        assert!(Font::from_psf(&header(0, 3)).is_err());
+        assert!(Font::from_psf(b"\x72\xb5\x4a\x86").is_err());
+        assert!(Font::from_psf(b"").is_err());
+    }
+}
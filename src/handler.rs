@@ -0,0 +1,69 @@
+//! A module containing the handler trait for server connections.
+use crate::command::{Command, Response};
+use crate::error::PixelflutError;
+use crate::pixel::Pixel;
+use alloc::string::String;
+
+/// Callbacks for a single Pixelflut server connection.
+///
+/// A handler is driven by `run_handler` on the
+/// [sync](crate::sync::PixelflutServerStream::run_handler) or
+/// [async](crate::async_tokio::PixelflutServerStream::run_handler) server stream.
+/// Only [`on_pixel`](Self::on_pixel) has to be implemented,
+/// all other commands are answered by the stream, unless the handler answers them itself.
+///
+/// # Examples
+///
+/// ```
+/// use pixelflut::{Command, Pixel, PixelflutHandler, Response};
+///
+/// #[derive(Default)]
+/// struct WriteOnly {
+///     pixels: usize,
+/// }
+///
+/// impl PixelflutHandler for WriteOnly {
+///     fn on_pixel(&mut self, _pixel: Pixel) {
+///         self.pixels += 1;
+///     }
+///
+///     fn on_command(&mut self, command: &Command) -> Option<Response> {
+///         match command {
+///             Command::GetPx(_) => Some(Response::Error("reading pixels is disabled".into())),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait PixelflutHandler {
+    /// Called once, before the first command is read.
+    fn on_connect(&mut self) {}
+
+    /// Called for every received pixel.
+    ///
+    /// The offset set by the client is already applied.
+    fn on_pixel(&mut self, pixel: Pixel);
+
+    /// Called for every parsed command, before the stream handles it.
+    ///
+    /// Returns a `Response` to answer the command instead of the stream,
+    /// or `None` to let the stream handle it as usual.
+    fn on_command(&mut self, _command: &Command) -> Option<Response> {
+        None
+    }
+
+    /// Called for [`Command::Custom`] commands without a handler registered on the stream.
+    ///
+    /// Returns a `Response` to answer the command,
+    /// or `None` to treat it as an invalid command.
+    /// Invalid commands are answered with an `ERROR` and counted by the error policy.
+    fn on_unknown_command(&mut self, _verb: &str, _args: &[String]) -> Option<Response> {
+        None
+    }
+
+    /// Called once, after the connection ended.
+    ///
+    /// `error` is the error that ended the connection,
+    /// or `None` on a clean end of the stream.
+    fn on_disconnect(&mut self, _error: Option<&PixelflutError>) {}
+}
@@ -1,29 +1,59 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc = include_str!("../README.md")]
 
+extern crate alloc;
 extern crate bstr;
-#[cfg(feature = "sync")]
-extern crate bufstream;
 extern crate bytes;
+#[cfg(feature = "smol-rt")]
+extern crate futures_io;
 #[cfg(feature = "image")]
 extern crate image;
 extern crate memchr;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "smol-rt")]
+extern crate smol;
 #[cfg(feature = "tokio-rt")]
 extern crate tokio;
-#[macro_use]
-extern crate lazy_static;
+#[cfg(feature = "tokio-rt")]
+extern crate tokio_util;
 
+#[cfg(feature = "smol-rt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol-rt")))]
+pub mod async_smol;
 #[cfg(any(doc, feature = "tokio-rt"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio-rt")))]
 pub mod async_tokio;
+#[cfg(any(feature = "tokio-rt", feature = "smol-rt"))]
+mod async_wrappers;
+mod canvas;
+#[cfg(any(feature = "tokio-rt", feature = "smol-rt"))]
+mod codec;
+mod color_names;
 mod command;
+#[cfg(any(feature = "tokio-rt", feature = "smol-rt"))]
+mod connection;
+mod draw;
 mod error;
+mod error_policy;
+mod font;
+mod handler;
+mod parser;
 mod pixel;
 mod pixel_buffer;
+mod rect;
 #[cfg(any(doc, feature = "sync"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
 pub mod sync;
 
+pub use canvas::{AlphaMode, Canvas};
+pub use command::{Command, CustomCommand, Response};
+pub use draw::{FloodFill, Painter};
 pub use error::{PixelflutError, PixelflutErrorKind, PixelflutResult};
+pub use error_policy::ErrorPolicy;
+pub use font::{Font, Text};
+pub use handler::PixelflutHandler;
 pub use pixel::{Color, Coordinate, Pixel};
-pub use pixel_buffer::PixelBuffer;
+pub use pixel_buffer::{PixelBuffer, PixelEncoding};
+pub use rect::{ColumnMajorIter, HilbertIter, Rect, RowMajorIter, ShuffledIter};
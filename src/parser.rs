@@ -0,0 +1,125 @@
+//! Byte based parsing helpers for the server hot path.
+//!
+//! The helpers work directly on the received bytes,
+//! without UTF-8 validation and without allocating.
+use crate::error::{PixelflutErrorKind, PixelflutResult};
+use crate::pixel::Color;
+
+/// Iterator over the whitespace separated arguments of a line.
+pub(crate) struct Arguments<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Arguments<'a> {
+    pub(crate) fn new(line: &'a [u8]) -> Arguments<'a> {
+        Arguments { rest: line }
+    }
+
+    /// Returns the next argument or fails with `WrongNumberOfArguments`.
+    pub(crate) fn next_required(&mut self) -> PixelflutResult<&'a [u8]> {
+        self.next()
+            .ok_or_else(|| PixelflutErrorKind::WrongNumberOfArguments.into())
+    }
+}
+
+impl<'a> Iterator for Arguments<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let start = self.rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        let rest = &self.rest[start..];
+        let end = rest
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        self.rest = &rest[end..];
+        Some(&rest[..end])
+    }
+}
+
+/// Parses an unsigned decimal number.
+pub(crate) fn parse_decimal(bytes: &[u8]) -> PixelflutResult<u32> {
+    if bytes.is_empty() {
+        return Err(PixelflutErrorKind::Parse.with_description("empty number"));
+    }
+    let mut value: u32 = 0;
+    for &b in bytes {
+        let digit = b.wrapping_sub(b'0');
+        if digit > 9 {
+            return Err(PixelflutErrorKind::Parse.with_description("invalid digit in number"));
+        }
+        value = value
+            .checked_mul(10)
+            .and_then(|value| value.checked_add(u32::from(digit)))
+            .ok_or_else(|| PixelflutErrorKind::Parse.with_description("number is to large"))?;
+    }
+    Ok(value)
+}
+
+fn hex_digit(b: u8) -> PixelflutResult<u8> {
+    match b {
+        b'0'..=b'9' => Ok(b - b'0'),
+        b'a'..=b'f' => Ok(b - b'a' + 10),
+        b'A'..=b'F' => Ok(b - b'A' + 10),
+        _ => Err(PixelflutErrorKind::Parse.with_description("invalid hex digit in color")),
+    }
+}
+
+fn hex_byte(bytes: &[u8]) -> PixelflutResult<u8> {
+    Ok(hex_digit(bytes[0])? << 4 | hex_digit(bytes[1])?)
+}
+
+/// Parses a `rrggbb` or `rrggbbaa` color.
+pub(crate) fn parse_color(bytes: &[u8]) -> PixelflutResult<Color> {
+    match bytes.len() {
+        6 => Ok(Color::rgb(
+            hex_byte(&bytes[0..2])?,
+            hex_byte(&bytes[2..4])?,
+            hex_byte(&bytes[4..6])?,
+        )),
+        8 => Ok(Color::rgba(
+            hex_byte(&bytes[0..2])?,
+            hex_byte(&bytes[2..4])?,
+            hex_byte(&bytes[4..6])?,
+            hex_byte(&bytes[6..8])?,
+        )),
+        _ => Err(PixelflutErrorKind::Parse.with_description("color length is wrong")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::{parse_color, parse_decimal, Arguments};
+    use crate::Color;
+
+    #[test]
+    fn arguments() {
+        let args: Vec<&[u8]> = Arguments::new(b"  PX\t12  34 ff0000\r\n").collect();
+        assert_eq!(args, vec![&b"PX"[..], b"12", b"34", b"ff0000"]);
+        assert_eq!(Arguments::new(b" \n").next(), None);
+    }
+
+    #[test]
+    fn decimal() {
+        assert_eq!(parse_decimal(b"0").unwrap(), 0);
+        assert_eq!(parse_decimal(b"4294967295").unwrap(), u32::MAX);
+        assert!(parse_decimal(b"4294967296").is_err());
+        assert!(parse_decimal(b"").is_err());
+        assert!(parse_decimal(b"-1").is_err());
+        assert!(parse_decimal(b"1a").is_err());
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(
+            parse_color(b"1122Ff").unwrap(),
+            Color::rgb(0x11, 0x22, 0xff)
+        );
+        assert_eq!(
+            parse_color(b"112233ee").unwrap(),
+            Color::rgba(0x11, 0x22, 0x33, 0xee)
+        );
+        assert!(parse_color(b"112g33").is_err());
+        assert!(parse_color(b"12345").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 //! A module that contians pixels for pixelflut.
+use crate::color_names;
 use crate::error::{PixelflutError, PixelflutErrorKind, PixelflutResult};
-use std::fmt;
-use std::str::FromStr;
+use crate::parser::{parse_color, parse_decimal, Arguments};
+use core::fmt;
+use core::ops::{Add, Sub};
+use core::str::FromStr;
 
 pub static MAX_FORMATTED_COORDINATE_SIZE: usize = 10;
 pub static MAX_FORMATTED_COLOR_SIZE: usize = 8;
@@ -15,6 +18,7 @@ pub static MAX_FORMATTED_PIXEL_SIZE_NEWLINE: usize = MAX_FORMATTED_PIXEL_SIZE +
 
 /// Pixelflut pixel containing a coordinate and a color
 #[derive(Copy, Clone, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pixel {
     pub position: Coordinate,
     pub color: Color,
@@ -27,6 +31,38 @@ impl Pixel {
     }
 }
 
+impl Pixel {
+    /// Parses a pixel in the form `<x> <y> <rrggbb(aa)>` directly from bytes.
+    ///
+    /// The [`FromStr`] implementation uses the same parser.
+    /// It skips the UTF-8 validation and does not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Pixel;
+    /// assert_eq!(
+    ///     Pixel::from_bytes(b"12 34 ff000a").unwrap(),
+    ///     Pixel::new((12, 34).into(), (255, 0, 10).into())
+    /// );
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> PixelflutResult<Pixel> {
+        let mut args = Arguments::new(bytes);
+        let pixel = Pixel::new(
+            Coordinate::new(
+                parse_decimal(args.next_required()?)?,
+                parse_decimal(args.next_required()?)?,
+            ),
+            parse_color(args.next_required()?)?,
+        );
+        if args.next().is_some() {
+            Err(PixelflutErrorKind::WrongNumberOfArguments.into())
+        } else {
+            Ok(pixel)
+        }
+    }
+}
+
 impl fmt::Display for Pixel {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} {}", self.position, self.color)
@@ -36,26 +72,9 @@ impl fmt::Display for Pixel {
 impl FromStr for Pixel {
     type Err = PixelflutError;
 
+    /// Parses a pixel, see [`Pixel::from_bytes`].
     fn from_str(s: &str) -> PixelflutResult<Pixel> {
-        let mut iter = s.split_whitespace();
-        let pixel = Pixel::new(
-            Coordinate::new(
-                iter.next()
-                    .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                    .parse()?,
-                iter.next()
-                    .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                    .parse()?,
-            ),
-            iter.next()
-                .ok_or(PixelflutErrorKind::WrongNumberOfArguments)?
-                .parse::<Color>()?,
-        );
-        if iter.next().is_some() {
-            Err(PixelflutErrorKind::WrongNumberOfArguments.into())
-        } else {
-            Ok(pixel)
-        }
+        Pixel::from_bytes(s.as_bytes())
     }
 }
 
@@ -67,6 +86,7 @@ impl<P: Into<Coordinate>, C: Into<Color>> From<(P, C)> for Pixel {
 
 /// coordinate on a pixelflut grid
 #[derive(Copy, Clone, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Coordinate {
     pub x: u32,
     pub y: u32,
@@ -77,6 +97,72 @@ impl Coordinate {
     pub fn new(x: u32, y: u32) -> Coordinate {
         Coordinate { x, y }
     }
+
+    /// Adds an offset to the `Coordinate`.
+    ///
+    /// Returns `None` if a component overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Coordinate;
+    /// let offset = Coordinate::new(100, 200);
+    /// assert_eq!(Coordinate::new(1, 2).checked_add(offset), Some(Coordinate::new(101, 202)));
+    /// assert_eq!(Coordinate::new(u32::MAX, 2).checked_add(offset), None);
+    /// ```
+    pub fn checked_add(self, offset: Coordinate) -> Option<Coordinate> {
+        Some(Coordinate::new(
+            self.x.checked_add(offset.x)?,
+            self.y.checked_add(offset.y)?,
+        ))
+    }
+
+    /// Subtracts an offset from the `Coordinate`.
+    ///
+    /// Returns `None` if a component would be negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Coordinate;
+    /// let offset = Coordinate::new(100, 200);
+    /// assert_eq!(Coordinate::new(101, 202).checked_sub(offset), Some(Coordinate::new(1, 2)));
+    /// assert_eq!(Coordinate::new(1, 202).checked_sub(offset), None);
+    /// ```
+    pub fn checked_sub(self, offset: Coordinate) -> Option<Coordinate> {
+        Some(Coordinate::new(
+            self.x.checked_sub(offset.x)?,
+            self.y.checked_sub(offset.y)?,
+        ))
+    }
+}
+
+impl Add for Coordinate {
+    type Output = Coordinate;
+
+    /// Adds an offset to the `Coordinate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component overflows, see [`Coordinate::checked_add`].
+    fn add(self, offset: Coordinate) -> Coordinate {
+        self.checked_add(offset)
+            .expect("overflow when adding an offset to a coordinate")
+    }
+}
+
+impl Sub for Coordinate {
+    type Output = Coordinate;
+
+    /// Subtracts an offset from the `Coordinate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component would be negative, see [`Coordinate::checked_sub`].
+    fn sub(self, offset: Coordinate) -> Coordinate {
+        self.checked_sub(offset)
+            .expect("overflow when subtracting an offset from a coordinate")
+    }
 }
 
 impl From<(u32, u32)> for Coordinate {
@@ -98,6 +184,9 @@ impl fmt::Display for Coordinate {
 }
 
 /// RGB color type with optional alpha channel
+///
+/// With the `serde` feature, colors are serialized as their hex form,
+/// e.g. `"ff0000"` or `"ff000080"` with an alpha channel.
 #[derive(Copy, Clone, PartialEq, Hash, Default)]
 pub struct Color {
     pub r: u8,
@@ -216,6 +305,360 @@ impl Color {
             None => (self.r, self.g, self.b, 255),
         }
     }
+
+    /// Composites the `Color` over a background color (source-over blending).
+    ///
+    /// The alpha channel of the result is only present, if the result is not opaque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// let background = Color::rgb(0, 0, 0);
+    /// assert_eq!(Color::rgba(255, 255, 255, 128).blend_over(background), Color::rgb(128, 128, 128));
+    /// assert_eq!(Color::rgb(255, 0, 0).blend_over(background), Color::rgb(255, 0, 0));
+    /// ```
+    pub fn blend_over(self, background: Color) -> Color {
+        let (r, g, b, a) = self.normalized();
+        match a {
+            255 => return Color::rgb(r, g, b),
+            0 => return background,
+            _ => {}
+        }
+        let (bg_r, bg_g, bg_b, bg_a) = background.normalized();
+
+        let src_a = u32::from(a);
+        // background alpha, weighted by the part of it that is not covered
+        let dst_a = u32::from(bg_a) * (255 - src_a);
+        // resulting alpha, scaled by 255
+        let out_a = src_a * 255 + dst_a;
+        if out_a == 0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+        let channel = |src: u8, dst: u8| -> u8 {
+            let value = u32::from(src) * src_a * 255 + u32::from(dst) * dst_a;
+            ((value + out_a / 2) / out_a) as u8
+        };
+
+        Color::packed(
+            channel(r, bg_r),
+            channel(g, bg_g),
+            channel(b, bg_b),
+            ((out_a + 127) / 255) as u8,
+        )
+    }
+
+    /// Returns the `Color` with its alpha channel set to `a`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// let background = Color::rgb(0, 0, 0);
+    /// assert_eq!(Color::rgb(255, 0, 0).with_alpha(128).blend_over(background), Color::rgb(128, 0, 0));
+    /// ```
+    pub const fn with_alpha(self, a: u8) -> Color {
+        Color::rgba(self.r, self.g, self.b, a)
+    }
+
+    /// Linearly interpolates between the `Color` at `t = 0.0` and `other` at `t = 1.0`.
+    ///
+    /// `t` is clamped to `0.0..=1.0`.
+    /// The result has an alpha channel, if one of the colors has one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// let black = Color::rgb(0, 0, 0);
+    /// let white = Color::rgb(255, 255, 255);
+    /// assert_eq!(black.lerp(white, 0.5), Color::rgb(128, 128, 128));
+    /// assert_eq!(black.lerp(white, 2.0), white);
+    /// ```
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |from: u8, to: u8| -> u8 {
+            let from = f32::from(from);
+            (from + (f32::from(to) - from) * t + 0.5) as u8
+        };
+        let color = Color::rgb(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+        );
+        match (self.a, other.a) {
+            (None, None) => color,
+            _ => color.with_alpha(channel(self.alpha(), other.alpha())),
+        }
+    }
+
+    /// Constructs an opaque `Color` from hue, saturation and value.
+    ///
+    /// The hue is given in degrees and wraps around.
+    /// Saturation and value are clamped to `0.0..=1.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::rgb(0, 255, 0));
+    /// assert_eq!(Color::from_hsv(-120.0, 1.0, 0.5), Color::rgb(0, 0, 128));
+    /// ```
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let v = v.clamp(0.0, 1.0);
+        let chroma = v * s.clamp(0.0, 1.0);
+        from_hue_chroma(h, chroma, v - chroma)
+    }
+
+    /// Returns hue, saturation and value of the `Color`.
+    ///
+    /// The hue is in degrees in `0.0..360.0`, saturation and value are in `0.0..=1.0`.
+    /// The alpha channel is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::rgb(0, 0, 255).to_hsv(), (240.0, 1.0, 1.0));
+    /// ```
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (hue, max, min) = hue_max_min(self);
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (hue, saturation, max)
+    }
+
+    /// Constructs an opaque `Color` from hue, saturation and lightness.
+    ///
+    /// The hue is given in degrees and wraps around.
+    /// Saturation and lightness are clamped to `0.0..=1.0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_hsl(0.0, 1.0, 0.5), Color::rgb(255, 0, 0));
+    /// assert_eq!(Color::from_hsl(0.0, 1.0, 1.0), Color::rgb(255, 255, 255));
+    /// ```
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Color {
+        let l = l.clamp(0.0, 1.0);
+        let chroma = (1.0 - abs(2.0 * l - 1.0)) * s.clamp(0.0, 1.0);
+        from_hue_chroma(h, chroma, l - chroma / 2.0)
+    }
+
+    /// Returns hue, saturation and lightness of the `Color`.
+    ///
+    /// The hue is in degrees in `0.0..360.0`, saturation and lightness are in `0.0..=1.0`.
+    /// The alpha channel is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::rgb(255, 0, 0).to_hsl(), (0.0, 1.0, 0.5));
+    /// ```
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (hue, max, min) = hue_max_min(self);
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - abs(2.0 * lightness - 1.0))
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Constructs a `Color` from an integer in the form `0xRRGGBBAA`.
+    ///
+    /// The alpha channel is only used, if it is not 255.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_rgba_u32(0x11223344), Color::rgba(0x11, 0x22, 0x33, 0x44));
+    /// assert_eq!(Color::from_rgba_u32(0x112233ff), Color::rgb(0x11, 0x22, 0x33));
+    /// ```
+    pub const fn from_rgba_u32(rgba: u32) -> Color {
+        let [r, g, b, a] = rgba.to_be_bytes();
+        Color::packed(r, g, b, a)
+    }
+
+    /// Returns the `Color` as an integer in the form `0xRRGGBBAA`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::rgb(0x11, 0x22, 0x33).to_rgba_u32(), 0x112233ff);
+    /// ```
+    pub const fn to_rgba_u32(self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.alpha()])
+    }
+
+    /// Constructs a `Color` from an integer in the form `0xAARRGGBB`.
+    ///
+    /// The alpha channel is only used, if it is not 255.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_argb_u32(0x44112233), Color::rgba(0x11, 0x22, 0x33, 0x44));
+    /// assert_eq!(Color::from_argb_u32(0xff112233), Color::rgb(0x11, 0x22, 0x33));
+    /// ```
+    pub const fn from_argb_u32(argb: u32) -> Color {
+        let [a, r, g, b] = argb.to_be_bytes();
+        Color::packed(r, g, b, a)
+    }
+
+    /// Returns the `Color` as an integer in the form `0xAARRGGBB`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::rgba(0x11, 0x22, 0x33, 0x44).to_argb_u32(), 0x44112233);
+    /// ```
+    pub const fn to_argb_u32(self) -> u32 {
+        u32::from_be_bytes([self.alpha(), self.r, self.g, self.b])
+    }
+
+    /// Constructs an opaque `Color` from a 16 bit RGB565 value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_rgb565(0xf800), Color::rgb(255, 0, 0));
+    /// assert_eq!(Color::from_rgb565(0x07e0), Color::rgb(0, 255, 0));
+    /// ```
+    pub const fn from_rgb565(rgb: u16) -> Color {
+        let r = (rgb >> 11) as u8 & 0x1f;
+        let g = (rgb >> 5) as u8 & 0x3f;
+        let b = rgb as u8 & 0x1f;
+        Color::rgb(r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2)
+    }
+
+    /// Returns the `Color` as a 16 bit RGB565 value.
+    ///
+    /// The channels are rounded to the nearest value and the alpha channel is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::rgb(0, 0, 255).to_rgb565(), 0x001f);
+    /// ```
+    pub const fn to_rgb565(self) -> u16 {
+        let r = (self.r as u16 * 31 + 127) / 255;
+        let g = (self.g as u16 * 63 + 127) / 255;
+        let b = (self.b as u16 * 31 + 127) / 255;
+        r << 11 | g << 5 | b
+    }
+
+    /// Parses a color leniently, like it might be written in CSS.
+    ///
+    /// Accepts `#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa`, with or without the `#`,
+    /// and CSS color names like `rebeccapurple` or `transparent`.
+    /// Case and surrounding whitespace are ignored.
+    /// The strict format of the protocol is parsed by [`FromStr`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Color;
+    /// assert_eq!(Color::from_css("#f80").unwrap(), Color::rgb(0xff, 0x88, 0x00));
+    /// assert_eq!(Color::from_css(" #FF8800 ").unwrap(), Color::rgb(0xff, 0x88, 0x00));
+    /// assert_eq!(Color::from_css("Orange").unwrap(), Color::rgb(0xff, 0xa5, 0x00));
+    /// assert!(Color::from_css("#orange").is_err());
+    /// ```
+    pub fn from_css(s: &str) -> PixelflutResult<Color> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_css_hex(hex);
+        }
+        if s.eq_ignore_ascii_case("transparent") {
+            return Ok(Color::rgba(0, 0, 0, 0));
+        }
+        match color_names::lookup(s) {
+            Some(rgb) => Ok(Color::from_argb_u32(0xff00_0000 | rgb)),
+            None => parse_css_hex(s),
+        }
+    }
+}
+
+/// Returns the hue in degrees and the largest and smallest channel in `0.0..=1.0`.
+fn hue_max_min(color: Color) -> (f32, f32, f32) {
+    let r = f32::from(color.r) / 255.0;
+    let g = f32::from(color.g) / 255.0;
+    let b = f32::from(color.b) / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+    (hue, max, min)
+}
+
+/// Absolute value of a float.
+///
+/// `f32::abs` is not available without `std` on the minimum supported Rust version.
+fn abs(value: f32) -> f32 {
+    if value < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Builds a color from the hue in degrees, the chroma
+/// and the amount `m` that is added to every channel.
+fn from_hue_chroma(h: f32, chroma: f32, m: f32) -> Color {
+    let h = h % 360.0;
+    let h = if h < 0.0 { h + 360.0 } else { h };
+    let sector = h / 60.0;
+    let x = chroma * (1.0 - abs(sector % 2.0 - 1.0));
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    // the cast saturates, so rounding errors can not overflow
+    let channel = |value: f32| (value * 255.0 + 0.5) as u8;
+    Color::rgb(channel(r + m), channel(g + m), channel(b + m))
+}
+
+/// Parses the hex part of a CSS color, with one or two digits per channel.
+fn parse_css_hex(hex: &str) -> PixelflutResult<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(PixelflutErrorKind::Parse.with_description("unknown color"));
+    }
+    match hex.len() {
+        3 | 4 => {
+            let value = u16::from_str_radix(hex, 16)?;
+            // every digit is repeated, so `f80` is `ff8800`
+            let digit = |shift: u32| ((value >> shift) & 0xf) as u8 * 0x11;
+            Ok(if hex.len() == 3 {
+                Color::rgb(digit(8), digit(4), digit(0))
+            } else {
+                Color::rgba(digit(12), digit(8), digit(4), digit(0))
+            })
+        }
+        6 | 8 => hex.parse(),
+        _ => Err(PixelflutErrorKind::Parse.with_description("color length is wrong")),
+    }
 }
 
 impl From<(u8, u8, u8)> for Color {
@@ -294,20 +737,7 @@ impl FromStr for Color {
     /// assert!("112g33".parse::<Color>().is_err());
     /// ```
     fn from_str(s: &str) -> PixelflutResult<Color> {
-        match s.len() {
-            6 => Ok(Color::rgb(
-                u8::from_str_radix(&s[0..2], 16)?,
-                u8::from_str_radix(&s[2..4], 16)?,
-                u8::from_str_radix(&s[4..6], 16)?,
-            )),
-            8 => Ok(Color::rgba(
-                u8::from_str_radix(&s[0..2], 16)?,
-                u8::from_str_radix(&s[2..4], 16)?,
-                u8::from_str_radix(&s[4..6], 16)?,
-                u8::from_str_radix(&s[6..8], 16)?,
-            )),
-            _ => Err(PixelflutErrorKind::Parse.with_description("color length is wrong")),
-        }
+        parse_color(s.as_bytes())
     }
 }
 
@@ -326,6 +756,34 @@ impl fmt::Debug for Color {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a hex color like \"rrggbb\" or \"rrggbbaa\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Color, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(ColorVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{Color, Coordinate, Pixel};
@@ -338,6 +796,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pixel_from_bytes() {
+        assert_eq!(
+            Pixel::new(Coordinate::new(10, 20), Color::rgba(0x11, 0x22, 0x33, 0x44)),
+            Pixel::from_bytes(b"10 20 11223344\n").unwrap()
+        );
+        assert!(Pixel::from_bytes(b"10 20").is_err());
+        assert!(Pixel::from_bytes(b"10 20 112233 1").is_err());
+    }
+
+    #[test]
+    fn test_pixel_from_bytes_matches_from_str() {
+        for line in [
+            "10 20 112233",
+            " 10\t20 11223344\n",
+            "+10 20 112233",
+            "10 -20 112233",
+            "10 20 +12233",
+            "10 20 -12233",
+            "10 20 1\u{e4}233",
+            "10\u{a0}20 112233",
+            "4294967296 20 112233",
+            "10 20",
+            "",
+        ] {
+            let from_str = line.parse::<Pixel>();
+            let from_bytes = Pixel::from_bytes(line.as_bytes());
+            match (from_str, from_bytes) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b, "{:?}", line),
+                (Err(a), Err(b)) => assert_eq!(a.kind(), b.kind(), "{:?}", line),
+                (a, b) => panic!("{:?}: {:?} != {:?}", line, a, b),
+            }
+        }
+        assert!("+10 20 112233".parse::<Pixel>().is_err());
+        assert!("+f2233".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn test_coordinate_add_sub() {
+        let position = Coordinate::new(10, 20);
+        let offset = Coordinate::new(1, 2);
+        assert_eq!(position + offset, Coordinate::new(11, 22));
+        assert_eq!(position - offset, Coordinate::new(9, 18));
+        assert_eq!(position - position, Coordinate::new(0, 0));
+        assert_eq!(
+            Coordinate::new(u32::MAX, 0).checked_add(Coordinate::new(0, 1)),
+            Some(Coordinate::new(u32::MAX, 1))
+        );
+        assert_eq!(Coordinate::new(0, u32::MAX).checked_add(offset), None);
+        assert_eq!(Coordinate::new(0, 5).checked_sub(offset), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_coordinate_sub_overflow() {
+        let _ = Coordinate::new(0, 0) - Coordinate::new(0, 1);
+    }
+
     #[test]
     fn test_color_rgb() {
         assert_eq!(
@@ -375,4 +891,168 @@ mod tests {
             Color::rgba(0x11, 0x22, 0x33, 0x44).normalized()
         );
     }
+
+    #[test]
+    fn test_color_blend_over() {
+        let background = Color::rgb(0x10, 0x20, 0x30);
+        assert_eq!(
+            Color::rgb(1, 2, 3).blend_over(background),
+            Color::rgb(1, 2, 3)
+        );
+        assert_eq!(
+            Color::rgba(1, 2, 3, 255).blend_over(background),
+            Color::rgb(1, 2, 3)
+        );
+        assert_eq!(Color::rgba(1, 2, 3, 0).blend_over(background), background);
+        assert_eq!(
+            Color::rgba(0xff, 0xff, 0xff, 0x80).blend_over(Color::rgb(0, 0, 0)),
+            Color::rgb(0x80, 0x80, 0x80)
+        );
+        assert_eq!(
+            Color::rgba(0xff, 0, 0, 0x40).blend_over(Color::rgb(0, 0, 0xff)),
+            Color::rgb(0x40, 0, 0xbf)
+        );
+
+        // blending over a transparent background keeps the color
+        assert_eq!(
+            Color::rgba(0x12, 0x34, 0x56, 0x78).blend_over(Color::rgba(0, 0, 0, 0)),
+            Color::rgba(0x12, 0x34, 0x56, 0x78)
+        );
+        // two half transparent layers
+        assert_eq!(
+            Color::rgba(0xff, 0, 0, 0x80).blend_over(Color::rgba(0, 0, 0xff, 0x80)),
+            Color::rgba(0xaa, 0, 0x55, 0xc0)
+        );
+        assert_eq!(
+            Color::rgba(0, 0, 0, 0).blend_over(Color::rgba(0, 0, 0, 0)),
+            Color::rgba(0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_color_lerp() {
+        let from = Color::rgb(0, 100, 200);
+        let to = Color::rgb(200, 100, 0);
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.25), Color::rgb(50, 100, 150));
+        assert_eq!(from.lerp(to, -1.0), from);
+        assert_eq!(
+            from.lerp(Color::rgba(200, 100, 0, 0), 0.5),
+            Color::rgba(100, 100, 100, 128)
+        );
+    }
+
+    #[test]
+    fn test_color_hsv() {
+        assert_eq!(Color::from_hsv(0.0, 1.0, 1.0), Color::rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(60.0, 1.0, 1.0), Color::rgb(255, 255, 0));
+        assert_eq!(Color::from_hsv(180.0, 1.0, 1.0), Color::rgb(0, 255, 255));
+        assert_eq!(Color::from_hsv(300.0, 1.0, 1.0), Color::rgb(255, 0, 255));
+        assert_eq!(Color::from_hsv(720.0, 1.0, 1.0), Color::rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(-0.0001, 1.0, 1.0), Color::rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(123.0, 0.0, 0.5), Color::rgb(128, 128, 128));
+        assert_eq!(Color::from_hsv(0.0, 2.0, 2.0), Color::rgb(255, 0, 0));
+        assert_eq!(Color::rgb(0, 0, 0).to_hsv(), (0.0, 0.0, 0.0));
+        assert_eq!(Color::rgb(255, 0, 255).to_hsv(), (300.0, 1.0, 1.0));
+
+        for color in [
+            Color::rgb(0x12, 0x34, 0x56),
+            Color::rgb(0xff, 0x80, 0x00),
+            Color::rgb(0x66, 0x33, 0x99),
+            Color::rgb(0xfe, 0xfe, 0xfd),
+        ] {
+            let (h, s, v) = color.to_hsv();
+            assert_eq!(Color::from_hsv(h, s, v), color);
+        }
+    }
+
+    #[test]
+    fn test_color_hsl() {
+        assert_eq!(Color::from_hsl(120.0, 1.0, 0.25), Color::rgb(0, 128, 0));
+        assert_eq!(Color::from_hsl(240.0, 0.5, 0.5), Color::rgb(64, 64, 191));
+        assert_eq!(Color::from_hsl(0.0, 0.0, 0.0), Color::rgb(0, 0, 0));
+        assert_eq!(Color::rgb(255, 255, 255).to_hsl(), (0.0, 0.0, 1.0));
+        assert_eq!(Color::rgb(0, 255, 255).to_hsl(), (180.0, 1.0, 0.5));
+
+        for color in [
+            Color::rgb(0x12, 0x34, 0x56),
+            Color::rgb(0xff, 0x80, 0x00),
+            Color::rgb(0x66, 0x33, 0x99),
+            Color::rgb(0x01, 0x02, 0x02),
+        ] {
+            let (h, s, l) = color.to_hsl();
+            assert_eq!(Color::from_hsl(h, s, l), color);
+        }
+    }
+
+    #[test]
+    fn test_color_packed_integers() {
+        let color = Color::rgba(0x11, 0x22, 0x33, 0x44);
+        assert_eq!(Color::from_rgba_u32(color.to_rgba_u32()), color);
+        assert_eq!(Color::from_argb_u32(color.to_argb_u32()), color);
+        assert_eq!(Color::rgb(1, 2, 3).to_argb_u32(), 0xff010203);
+        assert_eq!(Color::from_rgba_u32(0x010203ff).a, None);
+    }
+
+    #[test]
+    fn test_color_rgb565() {
+        assert_eq!(Color::from_rgb565(0xffff), Color::rgb(255, 255, 255));
+        assert_eq!(Color::from_rgb565(0), Color::rgb(0, 0, 0));
+        assert_eq!(Color::rgb(0x80, 0x80, 0x80).to_rgb565(), 0x8410);
+        assert_eq!(Color::rgba(255, 255, 255, 0).to_rgb565(), 0xffff);
+        for rgb in 0..=u16::MAX {
+            assert_eq!(Color::from_rgb565(rgb).to_rgb565(), rgb);
+        }
+    }
+
+    #[test]
+    fn test_color_from_css() {
+        assert_eq!(
+            Color::from_css("#abc").unwrap(),
+            Color::rgb(0xaa, 0xbb, 0xcc)
+        );
+        assert_eq!(
+            Color::from_css("#abcd").unwrap(),
+            Color::rgba(0xaa, 0xbb, 0xcc, 0xdd)
+        );
+        assert_eq!(
+            Color::from_css("A0B0C0").unwrap(),
+            Color::rgb(0xa0, 0xb0, 0xc0)
+        );
+        assert_eq!(
+            Color::from_css("#a0b0c0d0").unwrap(),
+            Color::rgba(0xa0, 0xb0, 0xc0, 0xd0)
+        );
+        assert_eq!(Color::from_css("red").unwrap(), Color::rgb(255, 0, 0));
+        assert_eq!(
+            Color::from_css("RebeccaPurple").unwrap(),
+            Color::rgb(0x66, 0x33, 0x99)
+        );
+        assert_eq!(
+            Color::from_css("\ttransparent\n").unwrap(),
+            Color::rgba(0, 0, 0, 0)
+        );
+        // names that are valid hex digits are colors
+        assert_eq!(
+            Color::from_css("add").unwrap(),
+            Color::rgb(0xaa, 0xdd, 0xdd)
+        );
+
+        assert!(Color::from_css("").is_err());
+        assert!(Color::from_css("#").is_err());
+        assert!(Color::from_css("#ab").is_err());
+        assert!(Color::from_css("#+bc").is_err());
+        assert!(Color::from_css("#12345").is_err());
+        assert!(Color::from_css("#gg0000").is_err());
+        assert!(Color::from_css("#red").is_err());
+        assert!(Color::from_css("#äbcd").is_err());
+        assert!(Color::from_css("notacolor").is_err());
+    }
+
+    #[test]
+    fn test_color_display_unchanged() {
+        assert_eq!(Color::from_css("#f80").unwrap().to_string(), "ff8800");
+        assert_eq!(Color::from_rgba_u32(0x11223344).to_string(), "11223344");
+    }
 }
@@ -1,11 +1,51 @@
 use crate::pixel::MAX_FORMATTED_PIXEL_SIZE_NEWLINE;
-use crate::Pixel;
-use std::io::Write;
-use std::iter::FromIterator;
-use std::sync::Arc;
+use crate::{Color, Coordinate, Pixel};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::iter::FromIterator;
 
 pub static PIXEL_BUFFER_DEFAULT_CAPACITY: usize = 8 * 1024;
 
+/// Size of a binary encoded pixel in bytes.
+pub const BINARY_PIXEL_SIZE: usize = 10;
+
+/// Wire format used for pixels.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PixelEncoding {
+    /// The text protocol: `PX <x> <y> <rrggbb(aa)>\n`
+    #[default]
+    Text,
+    /// The binary protocol, as supported by breakwater and pixelnuke:
+    /// `PB` followed by x and y as little-endian `u16` and the red,
+    /// green, blue and alpha bytes.
+    ///
+    /// Pixels with coordinates that do not fit into an `u16`
+    /// are written with the text protocol.
+    Binary,
+}
+
+/// Encodes a pixel in the binary protocol.
+///
+/// Returns `None` if the coordinates are to large for the binary protocol.
+pub(crate) fn encode_binary_pixel(pixel: &Pixel) -> Option<[u8; BINARY_PIXEL_SIZE]> {
+    let x = u16::try_from(pixel.position.x).ok()?.to_le_bytes();
+    let y = u16::try_from(pixel.position.y).ok()?.to_le_bytes();
+    let (r, g, b, a) = pixel.color.normalized();
+    Some([b'P', b'B', x[0], x[1], y[0], y[1], r, g, b, a])
+}
+
+/// Decodes a binary pixel, including the `PB` prefix.
+#[cfg_attr(not(any(feature = "sync", feature = "tokio-rt")), allow(dead_code))]
+pub(crate) fn decode_binary_pixel(bytes: &[u8; BINARY_PIXEL_SIZE]) -> Pixel {
+    Pixel::new(
+        Coordinate::new(
+            u16::from_le_bytes([bytes[2], bytes[3]]).into(),
+            u16::from_le_bytes([bytes[4], bytes[5]]).into(),
+        ),
+        Color::packed(bytes[6], bytes[7], bytes[8], bytes[9]),
+    )
+}
+
 /// Pixel buffer is a byte buffer that guarantees to contain valid pixelflut data.
 /// It is used as an internal buffer for the sync and async clients and
 /// can also be used for pre-format an array of pixels,
@@ -39,7 +79,7 @@ pub static PIXEL_BUFFER_DEFAULT_CAPACITY: usize = 8 * 1024;
 /// ```
 pub struct PixelBuffer {
     buffer: Vec<u8>,
-    number_writer: NumberWriter,
+    encoding: PixelEncoding,
 }
 
 impl PixelBuffer {
@@ -52,7 +92,7 @@ impl PixelBuffer {
     pub fn with_capacity(capacity: usize) -> PixelBuffer {
         PixelBuffer {
             buffer: Vec::with_capacity(capacity),
-            number_writer: NumberWriter::default(),
+            encoding: PixelEncoding::default(),
         }
     }
 
@@ -70,6 +110,29 @@ impl PixelBuffer {
         Self::with_capacity(PIXEL_BUFFER_DEFAULT_CAPACITY)
     }
 
+    /// Returns the encoding used for new pixels.
+    pub fn encoding(&self) -> PixelEncoding {
+        self.encoding
+    }
+
+    /// Sets the encoding used for new pixels.
+    ///
+    /// Pixels that are already in the buffer are not changed,
+    /// so text and binary pixels can be mixed in one buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{PixelBuffer, PixelEncoding, Pixel};
+    /// let mut buffer = PixelBuffer::new();
+    /// buffer.set_encoding(PixelEncoding::Binary);
+    /// buffer.write_pixel(&Pixel::new((258, 3).into(), (255, 0, 10).into()));
+    /// assert_eq!(buffer.as_slice(), b"PB\x02\x01\x03\x00\xff\x00\x0a\xff");
+    /// ```
+    pub fn set_encoding(&mut self, encoding: PixelEncoding) {
+        self.encoding = encoding;
+    }
+
     /// Extracts a slice containing the entire internal buffer.
     pub fn as_slice(&self) -> &[u8] {
         self.as_ref()
@@ -125,31 +188,28 @@ impl PixelBuffer {
     /// assert_eq!(buffer.as_slice(), b"PX 12 34 ff000a\nPX 13 34 ff000a\n")
     /// ```
     pub fn write_pixel(&mut self, pixel: &Pixel) {
+        if self.encoding == PixelEncoding::Binary {
+            if let Some(bytes) = encode_binary_pixel(pixel) {
+                self.buffer.extend_from_slice(&bytes);
+                return;
+            }
+        }
+
         let (x, y) = pixel.position.into();
         let color = pixel.color;
 
-        self.buffer.write_all(b"PX ").unwrap();
-        self.number_writer
-            .write_decimal(&mut self.buffer, x as usize)
-            .unwrap();
-        self.buffer.write_all(b" ").unwrap();
-        self.number_writer
-            .write_decimal(&mut self.buffer, y as usize)
-            .unwrap();
-        self.buffer.write_all(b" ").unwrap();
-        self.number_writer
-            .write_hex02(&mut self.buffer, color.r)
-            .unwrap();
-        self.number_writer
-            .write_hex02(&mut self.buffer, color.g)
-            .unwrap();
-        self.number_writer
-            .write_hex02(&mut self.buffer, color.b)
-            .unwrap();
+        self.buffer.extend_from_slice(b"PX ");
+        write_decimal(&mut self.buffer, x as usize);
+        self.buffer.push(b' ');
+        write_decimal(&mut self.buffer, y as usize);
+        self.buffer.push(b' ');
+        write_hex02(&mut self.buffer, color.r);
+        write_hex02(&mut self.buffer, color.g);
+        write_hex02(&mut self.buffer, color.b);
         if let Some(a) = color.a {
-            self.number_writer.write_hex02(&mut self.buffer, a).unwrap();
+            write_hex02(&mut self.buffer, a);
         }
-        self.buffer.write_all(b"\n").unwrap();
+        self.buffer.push(b'\n');
     }
 }
 
@@ -175,66 +235,97 @@ impl<P: Into<Pixel>> FromIterator<P> for PixelBuffer {
     }
 }
 
-lazy_static! {
-    static ref NUMBER_WRITER: NumberWriter = NumberWriter::create();
-}
-static NUMBER_WRITER_DEFAULT_MAX_DECIMAL: usize = 4096;
-
-/// Preformatted numbers for faster integer formatting.
-#[derive(Clone)]
-struct NumberWriter {
-    hex02: Arc<[[u8; 2]; 256]>,
-    decimal: Arc<Vec<String>>,
+impl<P: Into<Pixel>> Extend<P> for PixelBuffer {
+    fn extend<T: IntoIterator<Item = P>>(&mut self, iter: T) {
+        for pixel in iter {
+            self.write_pixel(&pixel.into());
+        }
+    }
 }
 
-impl NumberWriter {
-    /// Generate a new NumberWriter
-    pub fn with_decimal_size(decimal_size: usize) -> NumberWriter {
-        let mut hex02 = [[0u8; 2]; 256];
-        for (i, item) in hex02.iter_mut().enumerate() {
-            let str = format!("{:02x}", i);
-            item[0] = str.as_bytes()[0];
-            item[1] = str.as_bytes()[1];
-        }
+/// Numbers below this are taken from the preformatted table.
+const NUMBER_WRITER_DEFAULT_MAX_DECIMAL: usize = 4096;
 
-        let mut decimal = Vec::new();
-        for i in 0..decimal_size {
-            decimal.push(format!("{}", i));
-        }
+/// Preformatted lowercase hex digits of every byte.
+static HEX02: [[u8; 2]; 256] = hex02_table();
 
-        NumberWriter {
-            hex02: Arc::new(hex02),
-            decimal: Arc::new(decimal),
-        }
-    }
-
-    /// Generate a NumberWriter with default parameters
-    pub(crate) fn create() -> NumberWriter {
-        Self::with_decimal_size(NUMBER_WRITER_DEFAULT_MAX_DECIMAL)
-    }
+/// Preformatted decimal numbers for faster integer formatting.
+///
+/// The last byte of an entry is the number of digits.
+static DECIMAL: [[u8; 5]; NUMBER_WRITER_DEFAULT_MAX_DECIMAL] = decimal_table();
 
-    pub fn write_hex02(&self, mut writer: impl Write, value: u8) -> std::io::Result<()> {
-        writer.write_all(&self.hex02[value as usize][..])
+const fn hex02_table() -> [[u8; 2]; 256] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut table = [[0u8; 2]; 256];
+    let mut i = 0;
+    while i < table.len() {
+        table[i] = [DIGITS[i >> 4], DIGITS[i & 0xf]];
+        i += 1;
     }
+    table
+}
 
-    pub fn write_decimal(&self, mut writer: impl Write, value: usize) -> std::io::Result<()> {
-        if value < self.decimal.len() {
-            writer.write_all(self.decimal[value].as_bytes())
+const fn decimal_table() -> [[u8; 5]; NUMBER_WRITER_DEFAULT_MAX_DECIMAL] {
+    let mut table = [[0u8; 5]; NUMBER_WRITER_DEFAULT_MAX_DECIMAL];
+    let mut i = 0;
+    while i < table.len() {
+        let len = if i >= 1000 {
+            4
+        } else if i >= 100 {
+            3
+        } else if i >= 10 {
+            2
         } else {
-            panic!("value is to large");
+            1
+        };
+        let mut rest = i;
+        let mut pos = len;
+        while pos > 0 {
+            pos -= 1;
+            table[i][pos] = b'0' + (rest % 10) as u8;
+            rest /= 10;
         }
+        table[i][4] = len as u8;
+        i += 1;
     }
+    table
 }
 
-impl Default for NumberWriter {
-    fn default() -> Self {
-        NUMBER_WRITER.clone()
+fn write_hex02(buffer: &mut Vec<u8>, value: u8) {
+    buffer.extend_from_slice(&HEX02[value as usize]);
+}
+
+/// Writes a decimal number.
+///
+/// Small numbers are taken from the preformatted table,
+/// larger numbers are formatted digit by digit.
+fn write_decimal(buffer: &mut Vec<u8>, value: usize) {
+    if let Some(entry) = DECIMAL.get(value) {
+        buffer.extend_from_slice(&entry[..entry[4] as usize]);
+    } else {
+        // 20 digits are enough for a 64 bit usize
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        let mut rest = value;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        buffer.extend_from_slice(&digits[start..]);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Pixel, PixelBuffer};
+    use crate::pixel_buffer::{
+        decode_binary_pixel, encode_binary_pixel, write_decimal, write_hex02,
+        NUMBER_WRITER_DEFAULT_MAX_DECIMAL,
+    };
+    use crate::{Color, Pixel, PixelBuffer, PixelEncoding};
 
     #[test]
     fn pixelbuffer_from_iter() {
@@ -245,4 +336,72 @@ mod test {
         let buffer: PixelBuffer = v.into_iter().collect();
         assert_eq!(buffer.as_slice(), b"PX 12 34 ff000a\nPX 12 35 00ff0a\n");
     }
+
+    #[test]
+    fn binary_pixel_roundtrip() {
+        let pixel = Pixel::from(((0x1234, 0xabcd), (1, 2, 3)));
+        let bytes = encode_binary_pixel(&pixel).unwrap();
+        assert_eq!(bytes, *b"PB\x34\x12\xcd\xab\x01\x02\x03\xff");
+        assert_eq!(decode_binary_pixel(&bytes), pixel);
+
+        let pixel = Pixel::new((1, 2).into(), Color::rgba(1, 2, 3, 4));
+        assert_eq!(
+            decode_binary_pixel(&encode_binary_pixel(&pixel).unwrap()),
+            pixel
+        );
+
+        assert!(encode_binary_pixel(&Pixel::from(((0x10000, 0), (0, 0, 0)))).is_none());
+    }
+
+    #[test]
+    fn number_writer_decimal_boundaries() {
+        let max = NUMBER_WRITER_DEFAULT_MAX_DECIMAL;
+        for value in [
+            0,
+            9,
+            10,
+            max - 1,
+            max,
+            max + 1,
+            9999,
+            10000,
+            u32::MAX as usize,
+        ] {
+            let mut buf = Vec::new();
+            write_decimal(&mut buf, value);
+            assert_eq!(buf, value.to_string().as_bytes());
+        }
+    }
+
+    #[test]
+    fn number_writer_hex() {
+        for value in 0..=255u8 {
+            let mut buf = Vec::new();
+            write_hex02(&mut buf, value);
+            assert_eq!(buf, format!("{:02x}", value).as_bytes());
+        }
+    }
+
+    #[test]
+    fn pixelbuffer_large_coordinates() {
+        let mut buffer = PixelBuffer::new();
+        buffer.write_pixel(&Pixel::from(((4096, 7680), (0, 0, 0))));
+        buffer.write_pixel(&Pixel::from(((u32::MAX, 0), (0, 0, 0))));
+        assert_eq!(
+            buffer.as_slice(),
+            &b"PX 4096 7680 000000\nPX 4294967295 0 000000\n"[..]
+        );
+    }
+
+    #[test]
+    fn pixelbuffer_binary_falls_back_to_text() {
+        let mut buffer = PixelBuffer::new();
+        buffer.set_encoding(PixelEncoding::Binary);
+        buffer.write_pixel(&Pixel::from(((70000, 1), (0, 0, 0))));
+        buffer.write_pixel(&Pixel::from(((1, 1), (0, 0, 0))));
+        assert_eq!(
+            buffer.as_slice(),
+            &b"PX 70000 1 000000\nPB\x01\x00\x01\x00\x00\x00\x00\xff"[..]
+        );
+    }
 }
@@ -0,0 +1,648 @@
+//! Rectangular regions on a pixelflut grid.
+use crate::Coordinate;
+use core::cmp::{max, min};
+use core::convert::TryFrom;
+use core::iter::FusedIterator;
+
+/// Rectangular region on a pixelflut grid.
+///
+/// The region starts at its origin and spans `width` columns and `height` rows.
+/// A `Rect` never extends past `u32::MAX`, so its right and bottom edges always fit into an `u32`.
+///
+/// # Examples
+///
+/// ```
+/// use pixelflut::{Coordinate, Rect};
+/// let rect = Rect::new(10, 20, 3, 2);
+/// assert!(rect.contains(Coordinate::new(12, 21)));
+/// assert!(!rect.contains(Coordinate::new(13, 21)));
+/// assert_eq!(rect.iter().count(), 6);
+/// ```
+#[derive(Copy, Clone, PartialEq, Hash, Debug, Default)]
+pub struct Rect {
+    origin: Coordinate,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    /// Constructs a new `Rect` with its origin at `(x, y)`.
+    ///
+    /// The size is cut, if the `Rect` would extend past `u32::MAX`.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            origin: Coordinate::new(x, y),
+            width: min(width, u32::MAX - x),
+            height: min(height, u32::MAX - y),
+        }
+    }
+
+    /// Constructs a `Rect` covering a canvas with the given dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Rect;
+    /// assert_eq!(Rect::from_dimensions((800, 600)), Rect::new(0, 0, 800, 600));
+    /// ```
+    pub fn from_dimensions((width, height): (u32, u32)) -> Rect {
+        Rect::new(0, 0, width, height)
+    }
+
+    /// Constructs the smallest `Rect` containing both coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Rect};
+    /// let rect = Rect::from_corners(Coordinate::new(5, 1), Coordinate::new(2, 3));
+    /// assert_eq!(rect, Rect::new(2, 1, 4, 3));
+    /// ```
+    pub fn from_corners(a: Coordinate, b: Coordinate) -> Rect {
+        let x = min(a.x, b.x);
+        let y = min(a.y, b.y);
+        Rect::new(
+            x,
+            y,
+            (max(a.x, b.x) - x).saturating_add(1),
+            (max(a.y, b.y) - y).saturating_add(1),
+        )
+    }
+
+    /// Returns the top left corner.
+    pub fn origin(&self) -> Coordinate {
+        self.origin
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the dimensions as `(width, height)`.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Returns the x coordinate of the first column right of the `Rect`.
+    pub fn right(&self) -> u32 {
+        self.origin.x + self.width
+    }
+
+    /// Returns the y coordinate of the first row below the `Rect`.
+    pub fn bottom(&self) -> u32 {
+        self.origin.y + self.height
+    }
+
+    /// Returns the number of coordinates in the `Rect`.
+    pub fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+
+    /// Returns `true` if the `Rect` contains no coordinates.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns `true` if the coordinate is inside of the `Rect`.
+    pub fn contains(&self, position: Coordinate) -> bool {
+        position.x >= self.origin.x
+            && position.y >= self.origin.y
+            && position.x < self.right()
+            && position.y < self.bottom()
+    }
+
+    /// Returns `true` if every coordinate of `other` is inside of the `Rect`.
+    ///
+    /// An empty `Rect` is contained in every `Rect`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Rect;
+    /// let rect = Rect::new(0, 0, 10, 10);
+    /// assert!(rect.contains_rect(Rect::new(5, 5, 5, 5)));
+    /// assert!(!rect.contains_rect(Rect::new(5, 5, 6, 5)));
+    /// ```
+    pub fn contains_rect(&self, other: Rect) -> bool {
+        other.is_empty()
+            || (other.origin.x >= self.origin.x
+                && other.origin.y >= self.origin.y
+                && other.right() <= self.right()
+                && other.bottom() <= self.bottom())
+    }
+
+    /// Returns the region covered by both rects or `None` if they do not overlap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Rect;
+    /// let a = Rect::new(0, 0, 10, 10);
+    /// assert_eq!(a.intersection(Rect::new(5, 8, 10, 10)), Some(Rect::new(5, 8, 5, 2)));
+    /// assert_eq!(a.intersection(Rect::new(10, 0, 10, 10)), None);
+    /// ```
+    pub fn intersection(&self, other: Rect) -> Option<Rect> {
+        let x = max(self.origin.x, other.origin.x);
+        let y = max(self.origin.y, other.origin.y);
+        let right = min(self.right(), other.right());
+        let bottom = min(self.bottom(), other.bottom());
+        if x < right && y < bottom {
+            Some(Rect::new(x, y, right - x, bottom - y))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest `Rect` containing both rects.
+    ///
+    /// Empty rects are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Rect;
+    /// let a = Rect::new(0, 0, 2, 2);
+    /// assert_eq!(a.union(Rect::new(5, 1, 1, 4)), Rect::new(0, 0, 6, 5));
+    /// assert_eq!(a.union(Rect::new(5, 1, 0, 4)), a);
+    /// ```
+    pub fn union(&self, other: Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+        let x = min(self.origin.x, other.origin.x);
+        let y = min(self.origin.y, other.origin.y);
+        Rect::new(
+            x,
+            y,
+            max(self.right(), other.right()) - x,
+            max(self.bottom(), other.bottom()) - y,
+        )
+    }
+
+    /// Clips the `Rect` to a canvas with the given dimensions.
+    ///
+    /// Returns `None` if the `Rect` is completely outside of the canvas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::Rect;
+    /// assert_eq!(Rect::new(790, 10, 20, 20).clip((800, 600)), Some(Rect::new(790, 10, 10, 20)));
+    /// assert_eq!(Rect::new(800, 10, 20, 20).clip((800, 600)), None);
+    /// ```
+    pub fn clip(&self, dimensions: (u32, u32)) -> Option<Rect> {
+        self.intersection(Rect::from_dimensions(dimensions))
+    }
+
+    /// Returns an iterator over all coordinates, row by row.
+    pub fn iter(&self) -> RowMajorIter {
+        RowMajorIter {
+            rect: *self,
+            index: 0,
+            end: self.area(),
+        }
+    }
+
+    /// Returns an iterator over all coordinates, column by column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Rect};
+    /// let coordinates: Vec<Coordinate> = Rect::new(1, 1, 2, 2).iter_column_major().collect();
+    /// assert_eq!(coordinates, [(1, 1).into(), (1, 2).into(), (2, 1).into(), (2, 2).into()]);
+    /// ```
+    pub fn iter_column_major(&self) -> ColumnMajorIter {
+        ColumnMajorIter {
+            rect: *self,
+            index: 0,
+            end: self.area(),
+        }
+    }
+
+    /// Returns an iterator over all coordinates in a pseudo random order.
+    ///
+    /// The same seed always results in the same order.
+    /// The order is computed on the fly, so no memory is allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pixelflut::{Coordinate, Rect};
+    /// let rect = Rect::new(0, 0, 80, 60);
+    /// let mut coordinates: Vec<Coordinate> = rect.iter_shuffled(42).collect();
+    /// assert_ne!(coordinates, rect.iter().collect::<Vec<_>>());
+    /// coordinates.sort_by_key(|position| (position.y, position.x));
+    /// assert_eq!(coordinates, rect.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn iter_shuffled(&self, seed: u64) -> ShuffledIter {
+        ShuffledIter::new(*self, seed)
+    }
+
+    /// Returns an iterator over all coordinates along a Hilbert curve.
+    ///
+    /// Consecutive coordinates are always neighbours, if the `Rect` is a square
+    /// with a power of two as its size.
+    /// Other rects are covered by the part of the curve that lies inside of them.
+    pub fn iter_hilbert(&self) -> HilbertIter {
+        HilbertIter::new(*self)
+    }
+}
+
+impl IntoIterator for Rect {
+    type Item = Coordinate;
+    type IntoIter = RowMajorIter;
+
+    fn into_iter(self) -> RowMajorIter {
+        self.iter()
+    }
+}
+
+/// Returns the remaining number of items as a size hint.
+fn size_hint(remaining: u64) -> (usize, Option<usize>) {
+    match usize::try_from(remaining) {
+        Ok(remaining) => (remaining, Some(remaining)),
+        Err(_) => (usize::MAX, None),
+    }
+}
+
+/// Iterator over the coordinates of a [`Rect`], row by row.
+///
+/// Created by [`Rect::iter`].
+#[derive(Clone, Debug)]
+pub struct RowMajorIter {
+    rect: Rect,
+    index: u64,
+    end: u64,
+}
+
+impl Iterator for RowMajorIter {
+    type Item = Coordinate;
+
+    fn next(&mut self) -> Option<Coordinate> {
+        if self.index == self.end {
+            return None;
+        }
+        let width = u64::from(self.rect.width);
+        let position = Coordinate::new(
+            self.rect.origin.x + (self.index % width) as u32,
+            self.rect.origin.y + (self.index / width) as u32,
+        );
+        self.index += 1;
+        Some(position)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.end - self.index)
+    }
+}
+
+impl FusedIterator for RowMajorIter {}
+
+/// Iterator over the coordinates of a [`Rect`], column by column.
+///
+/// Created by [`Rect::iter_column_major`].
+#[derive(Clone, Debug)]
+pub struct ColumnMajorIter {
+    rect: Rect,
+    index: u64,
+    end: u64,
+}
+
+impl Iterator for ColumnMajorIter {
+    type Item = Coordinate;
+
+    fn next(&mut self) -> Option<Coordinate> {
+        if self.index == self.end {
+            return None;
+        }
+        let height = u64::from(self.rect.height);
+        let position = Coordinate::new(
+            self.rect.origin.x + (self.index / height) as u32,
+            self.rect.origin.y + (self.index % height) as u32,
+        );
+        self.index += 1;
+        Some(position)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.end - self.index)
+    }
+}
+
+impl FusedIterator for ColumnMajorIter {}
+
+/// Iterator over the coordinates of a [`Rect`] in a pseudo random order.
+///
+/// Created by [`Rect::iter_shuffled`].
+#[derive(Clone, Debug)]
+pub struct ShuffledIter {
+    rect: Rect,
+    keys: [u64; 3],
+    mask: u64,
+    shift: u32,
+    index: u64,
+    remaining: u64,
+}
+
+impl ShuffledIter {
+    fn new(rect: Rect, seed: u64) -> ShuffledIter {
+        let area = rect.area();
+        // the indices are permuted in the smallest power of two that fits the area
+        let bits = 64 - area.saturating_sub(1).leading_zeros();
+        let mask = u64::MAX >> (64 - bits.max(1));
+        let mut state = seed;
+        ShuffledIter {
+            rect,
+            keys: [
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+            ],
+            mask: if bits == 0 { 0 } else { mask },
+            shift: bits / 2 + 1,
+            index: 0,
+            remaining: area,
+        }
+    }
+
+    /// Maps an index to another index with the same number of bits.
+    ///
+    /// Every step is a bijection, so every index is returned exactly once.
+    fn permute(&self, mut index: u64) -> u64 {
+        for key in self.keys {
+            index = index.wrapping_add(key) & self.mask;
+            index ^= index >> self.shift;
+            index = index.wrapping_mul(0x9e37_79b9_7f4a_7c15) & self.mask;
+        }
+        index
+    }
+}
+
+impl Iterator for ShuffledIter {
+    type Item = Coordinate;
+
+    fn next(&mut self) -> Option<Coordinate> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let area = self.rect.area();
+        loop {
+            let index = self.permute(self.index);
+            self.index = self.index.wrapping_add(1);
+            // indices outside of the area are skipped
+            if index < area {
+                self.remaining -= 1;
+                let width = u64::from(self.rect.width);
+                return Some(Coordinate::new(
+                    self.rect.origin.x + (index % width) as u32,
+                    self.rect.origin.y + (index / width) as u32,
+                ));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.remaining)
+    }
+}
+
+impl FusedIterator for ShuffledIter {}
+
+/// Advances the state and returns the next pseudo random number.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Iterator over the coordinates of a [`Rect`] along a Hilbert curve.
+///
+/// Created by [`Rect::iter_hilbert`].
+#[derive(Clone, Debug)]
+pub struct HilbertIter {
+    rect: Rect,
+    order: u32,
+    index: u128,
+    end: u128,
+    remaining: u64,
+}
+
+impl HilbertIter {
+    fn new(rect: Rect) -> HilbertIter {
+        // the curve fills the smallest square with a power of two as its size
+        let size = u64::from(max(rect.width, rect.height)).next_power_of_two();
+        let order = size.trailing_zeros();
+        HilbertIter {
+            rect,
+            order,
+            index: 0,
+            end: if rect.is_empty() { 0 } else { 1 << (2 * order) },
+            remaining: rect.area(),
+        }
+    }
+}
+
+impl Iterator for HilbertIter {
+    type Item = Coordinate;
+
+    fn next(&mut self) -> Option<Coordinate> {
+        let width = u64::from(self.rect.width);
+        let height = u64::from(self.rect.height);
+        while self.index < self.end {
+            let (x, y) = hilbert_position(self.index, self.order);
+            if x < width && y < height {
+                self.index += 1;
+                self.remaining -= 1;
+                return Some(Coordinate::new(
+                    self.rect.origin.x + x as u32,
+                    self.rect.origin.y + y as u32,
+                ));
+            }
+            // Aligned blocks of 4^level indices cover aligned squares of 2^level,
+            // so the largest block starting here that lies outside can be skipped.
+            let mut level = 0;
+            while level < self.order && self.index.trailing_zeros() >= 2 * (level + 1) {
+                let block = !((1u64 << (level + 1)) - 1);
+                if x & block < width && y & block < height {
+                    break;
+                }
+                level += 1;
+            }
+            self.index += 1 << (2 * level);
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.remaining)
+    }
+}
+
+impl FusedIterator for HilbertIter {}
+
+/// Converts an index on a Hilbert curve of the given order to its position.
+fn hilbert_position(index: u128, order: u32) -> (u64, u64) {
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut t = index;
+    for level in 0..order {
+        let size = 1u64 << level;
+        let rx = (t >> 1) as u64 & 1;
+        let ry = (t as u64 ^ rx) & 1;
+        if ry == 0 {
+            if rx == 1 {
+                x = size - 1 - x;
+                y = size - 1 - y;
+            }
+            core::mem::swap(&mut x, &mut y);
+        }
+        x += size * rx;
+        y += size * ry;
+        t >>= 2;
+    }
+    (x, y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rect;
+    use crate::Coordinate;
+    use alloc::vec::Vec;
+
+    fn sorted(mut coordinates: Vec<Coordinate>) -> Vec<(u32, u32)> {
+        let mut coordinates: Vec<(u32, u32)> = coordinates.drain(..).map(Into::into).collect();
+        coordinates.sort_unstable();
+        coordinates
+    }
+
+    #[test]
+    fn new_saturates() {
+        let rect = Rect::new(u32::MAX - 1, 0, 10, 10);
+        assert_eq!(rect.dimensions(), (1, 10));
+        assert_eq!(rect.right(), u32::MAX);
+        assert_eq!(Rect::new(u32::MAX, u32::MAX, 1, 1).area(), 0);
+        assert_eq!(
+            Rect::from_corners(Coordinate::new(0, 0), Coordinate::new(u32::MAX, 0)).width(),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn geometry() {
+        let rect = Rect::new(10, 20, 30, 40);
+        assert_eq!((rect.right(), rect.bottom()), (40, 60));
+        assert_eq!(rect.area(), 1200);
+        assert!(rect.contains(Coordinate::new(10, 20)));
+        assert!(rect.contains(Coordinate::new(39, 59)));
+        assert!(!rect.contains(Coordinate::new(40, 59)));
+        assert!(!rect.contains(Coordinate::new(9, 20)));
+        assert!(!Rect::new(10, 20, 0, 40).contains(Coordinate::new(10, 20)));
+
+        assert!(rect.contains_rect(rect));
+        assert!(rect.contains_rect(Rect::new(100, 100, 0, 0)));
+        assert!(!rect.contains_rect(Rect::new(9, 20, 2, 2)));
+
+        assert_eq!(rect.intersection(rect), Some(rect));
+        assert_eq!(rect.intersection(Rect::new(0, 0, 10, 100)), None);
+        assert_eq!(
+            rect.intersection(Rect::new(0, 0, 11, 21)),
+            Some(Rect::new(10, 20, 1, 1))
+        );
+        assert_eq!(rect.intersection(Rect::new(20, 30, 0, 0)), None);
+
+        assert_eq!(rect.union(Rect::default()), rect);
+        assert_eq!(Rect::default().union(rect), rect);
+        assert_eq!(
+            rect.union(Rect::new(0, 100, 1, 1)),
+            Rect::new(0, 20, 40, 81)
+        );
+
+        assert_eq!(rect.clip((800, 600)), Some(rect));
+        assert_eq!(rect.clip((20, 30)), Some(Rect::new(10, 20, 10, 10)));
+        assert_eq!(rect.clip((0, 0)), None);
+    }
+
+    #[test]
+    fn iter_orders() {
+        let rect = Rect::new(1, 2, 3, 2);
+        let rows: Vec<(u32, u32)> = rect.into_iter().map(Into::into).collect();
+        assert_eq!(rows, [(1, 2), (2, 2), (3, 2), (1, 3), (2, 3), (3, 3)]);
+        let columns: Vec<(u32, u32)> = rect.iter_column_major().map(Into::into).collect();
+        assert_eq!(columns, [(1, 2), (1, 3), (2, 2), (2, 3), (3, 2), (3, 3)]);
+        assert_eq!(rect.iter().size_hint(), (6, Some(6)));
+
+        assert_eq!(Rect::new(5, 5, 0, 10).iter().next(), None);
+        assert_eq!(Rect::new(5, 5, 10, 0).iter_column_major().next(), None);
+        assert_eq!(Rect::new(5, 5, 0, 0).iter_shuffled(1).next(), None);
+        assert_eq!(Rect::new(5, 5, 0, 10).iter_hilbert().next(), None);
+    }
+
+    #[test]
+    fn iter_shuffled() {
+        for rect in [
+            Rect::new(0, 0, 1, 1),
+            Rect::new(3, 4, 2, 1),
+            Rect::new(3, 4, 17, 13),
+            Rect::new(0, 0, 64, 64),
+            Rect::new(7, 0, 1, 1000),
+        ] {
+            let shuffled: Vec<Coordinate> = rect.iter_shuffled(7).collect();
+            assert_eq!(shuffled.len() as u64, rect.area());
+            assert_eq!(sorted(shuffled), sorted(rect.iter().collect()));
+        }
+
+        let rect = Rect::new(0, 0, 100, 100);
+        let first: Vec<Coordinate> = rect.iter_shuffled(1).collect();
+        assert_eq!(first, rect.iter_shuffled(1).collect::<Vec<_>>());
+        assert_ne!(first, rect.iter_shuffled(2).collect::<Vec<_>>());
+        assert_ne!(first, rect.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iter_hilbert() {
+        let square: Vec<(u32, u32)> = Rect::new(0, 0, 2, 2)
+            .iter_hilbert()
+            .map(Into::into)
+            .collect();
+        assert_eq!(square, [(0, 0), (0, 1), (1, 1), (1, 0)]);
+
+        // neighbours on the full curve
+        let curve: Vec<Coordinate> = Rect::new(10, 10, 32, 32).iter_hilbert().collect();
+        assert_eq!(curve.len(), 1024);
+        for pair in curve.windows(2) {
+            let distance = (pair[0].x as i64 - pair[1].x as i64).abs()
+                + (pair[0].y as i64 - pair[1].y as i64).abs();
+            assert_eq!(distance, 1);
+        }
+
+        for rect in [
+            Rect::new(3, 4, 17, 13),
+            Rect::new(7, 0, 1, 1000),
+            Rect::new(0, 7, 1000, 3),
+        ] {
+            let coordinates: Vec<Coordinate> = rect.iter_hilbert().collect();
+            assert_eq!(coordinates.len() as u64, rect.area());
+            assert_eq!(sorted(coordinates), sorted(rect.iter().collect()));
+        }
+    }
+
+    #[test]
+    fn iter_hilbert_large() {
+        // large blocks outside of the rect are skipped
+        let rect = Rect::new(0, 0, 1, u32::MAX);
+        assert_eq!(rect.iter_hilbert().take(1000).count(), 1000);
+        let last = Rect::new(u32::MAX - 2, u32::MAX - 2, 2, 2);
+        assert_eq!(last.iter_hilbert().count(), 4);
+    }
+}
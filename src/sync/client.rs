@@ -1,26 +1,50 @@
 //! Contains the sync client for pixelflut.
-use bufstream::BufStream;
-
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::net::{TcpStream, ToSocketAddrs};
 
 use crate::command::{Command, Response};
 use crate::error::PixelflutErrorKind;
 use crate::pixel::Pixel;
-use crate::{Color, PixelflutResult};
+use crate::{Color, PixelBuffer, PixelEncoding, PixelflutResult};
 
 /// Sync Pixelflut client.
-pub struct PixelflutClient {
-    stream: BufStream<TcpStream>,
+///
+/// The client can be used with any stream implementing [`Read`] and [`Write`],
+/// e.g. a `UnixStream` or a serial port.
+/// Use [`connect`](PixelflutClient::connect) for a plain TCP connection.
+///
+/// Pixels are buffered and the remaining ones are send when the client is dropped.
+/// Errors are ignored then, so use [`flush`](PixelflutClient::flush) to handle them.
+pub struct PixelflutClient<S: Read + Write = TcpStream> {
+    stream: BufReader<S>,
+    write_buf: PixelBuffer,
 }
 
-impl PixelflutClient {
+impl PixelflutClient<TcpStream> {
     /// connects to a Pixelflut host at address `addr`
     pub fn connect(addr: impl ToSocketAddrs) -> PixelflutResult<PixelflutClient> {
         let stream = TcpStream::connect(addr)?;
-        Ok(PixelflutClient {
-            stream: BufStream::new(stream),
-        })
+        Ok(PixelflutClient::new(stream))
+    }
+}
+
+impl<S: Read + Write> PixelflutClient<S> {
+    /// Creates a client on an already connected stream.
+    pub fn new(stream: S) -> PixelflutClient<S> {
+        PixelflutClient {
+            stream: BufReader::new(stream),
+            write_buf: PixelBuffer::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.stream.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.stream.get_mut()
     }
 
     /// Asks the server for the dimensions of the canvas.
@@ -32,37 +56,148 @@ impl PixelflutClient {
     /// # Returns
     /// Ok((width, height)) on success
     pub fn dimensions(&mut self) -> PixelflutResult<(u32, u32)> {
-        self.stream.write_fmt(format_args!("{}\n", Command::Size))?;
-        self.stream.flush()?;
+        self.write_command(&Command::Size)?;
+        match self.read_response()? {
+            Response::Size { w, h } => Ok((w, h)),
+            Response::Error(_err) => Err(PixelflutErrorKind::ServerError.into()),
+            _ => Err(PixelflutErrorKind::State.into()),
+        }
+    }
+
+    /// Reads the color of a pixel from the server.
+    ///
+    /// A `PX <x> <y>` command is send to the server.
+    /// If the server replies with a `PX <x> <y> <color>` packet,
+    /// the color will be returned.
+    pub fn get(&mut self, x: u32, y: u32) -> PixelflutResult<Color> {
+        self.write_command(&Command::GetPx((x, y).into()))?;
+        match self.read_response()? {
+            Response::Px(pixel) if pixel.position == (x, y).into() => Ok(pixel.color),
+            Response::Error(_err) => Err(PixelflutErrorKind::ServerError.into()),
+            _ => Err(PixelflutErrorKind::State.into()),
+        }
+    }
+
+    /// Asks the server for its help text.
+    ///
+    /// A `HELP` command is send to the server.
+    /// The `HELP <line>` lines of the reply are returned without the prefix.
+    pub fn help(&mut self) -> PixelflutResult<String> {
+        self.write_command(&Command::Help)?;
+        let mut lines = String::new();
+        loop {
+            let start = lines.len();
+            if self.stream.read_line(&mut lines)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected help").into());
+            }
+            let line = lines[start..].trim_end_matches(&['\r', '\n'][..]);
+            if line == "HELP" || !line.starts_with("HELP ") {
+                break;
+            }
+        }
+        match lines.trim_end_matches(&['\r', '\n'][..]).parse()? {
+            Response::Help(text) => Ok(text.into_owned()),
+            Response::Error(_err) => Err(PixelflutErrorKind::ServerError.into()),
+            _ => Err(PixelflutErrorKind::State.into()),
+        }
+    }
+
+    /// Sends a `Command` and flushes the stream.
+    fn write_command(&mut self, command: &Command) -> PixelflutResult<()> {
+        self.write_buffered()?;
+        let stream = self.stream.get_mut();
+        stream.write_fmt(format_args!("{}\n", command))?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Reads a single `Response` line from the server.
+    fn read_response(&mut self) -> PixelflutResult<Response> {
         let mut line = String::new();
         let n = self.stream.read_line(&mut line)?;
         if n > 0 {
-            let response: Response = line[0..n - 1].parse()?;
-            match response {
-                Response::Size { w, h } => Ok((w, h)),
-                Response::Error(_err) => Err(PixelflutErrorKind::ServerError.into()),
-            }
+            Ok(line[0..n - 1].parse()?)
         } else {
-            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected size").into())
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "expected response").into())
         }
     }
 
+    /// Sets the offset the server adds to all following pixels.
+    ///
+    /// Buffered pixels are flushed before the `OFFSET <x> <y>` command is send,
+    /// so they are still drawn with the previous offset.
+    pub fn set_offset(&mut self, x: u32, y: u32) -> PixelflutResult<()> {
+        self.write_command(&Command::Offset((x, y).into()))
+    }
+
+    /// Sets the encoding used for all following pixels.
+    ///
+    /// The server must support the binary protocol if
+    /// [`PixelEncoding::Binary`] is used.
+    pub fn set_encoding(&mut self, encoding: PixelEncoding) {
+        self.write_buf.set_encoding(encoding);
+    }
+
     /// Writes a Pixel to the server.
     ///
     /// A buffered stream is used for sending.
-    /// The pixel is only send if the buffer is full or [flush] is called.
+    /// The pixel is only send if the buffer is full, [flush] is called
+    /// or the client is dropped.
     ///
     /// [flush]: Self::flush
     pub fn set(&mut self, x: u32, y: u32, color: impl Into<Color>) -> PixelflutResult<()> {
-        let pixel = Pixel::new((x, y).into(), color.into());
-        self.stream
-            .write_fmt(format_args!("{}\n", Command::Px(pixel)))?;
+        self.set_pixel(&Pixel::new((x, y).into(), color.into()))
+    }
+
+    /// Writes multiple pixels to the server, e.g. a shape drawn by a [`Painter`].
+    ///
+    /// The pixels are buffered like with [`set`](Self::set).
+    ///
+    /// [`Painter`]: crate::Painter
+    pub fn set_pixels(&mut self, pixels: impl IntoIterator<Item = Pixel>) -> PixelflutResult<()> {
+        for pixel in pixels {
+            self.set_pixel(&pixel)?;
+        }
+        Ok(())
+    }
+
+    fn set_pixel(&mut self, pixel: &Pixel) -> PixelflutResult<()> {
+        if self.write_buf.is_capacity_reached() {
+            self.write_buffered()?;
+        }
+        self.write_buf.write_pixel(pixel);
+        Ok(())
+    }
+
+    /// Writes a pre-formatted `PixelBuffer` to the server.
+    ///
+    /// Pixels that were set before are send first.
+    pub fn write_buffer(&mut self, buffer: &PixelBuffer) -> PixelflutResult<()> {
+        self.write_buffered()?;
+        self.stream.get_mut().write_all(buffer.as_slice())?;
+        Ok(())
+    }
+
+    /// Writes the internal buffer to the stream, without flushing the stream.
+    fn write_buffered(&mut self) -> PixelflutResult<()> {
+        if !self.write_buf.is_empty() {
+            self.stream.get_mut().write_all(self.write_buf.as_slice())?;
+        }
+        self.write_buf.clear();
         Ok(())
     }
 
     /// Flushes the internal buffer to the server.
     pub fn flush(&mut self) -> PixelflutResult<()> {
-        self.stream.flush()?;
+        self.write_buffered()?;
+        self.stream.get_mut().flush()?;
         Ok(())
     }
 }
+
+impl<S: Read + Write> Drop for PixelflutClient<S> {
+    fn drop(&mut self) {
+        // like a `BufWriter`, the buffered pixels are not lost
+        let _ = self.flush();
+    }
+}
@@ -3,4 +3,4 @@ mod client;
 mod server;
 
 pub use self::client::PixelflutClient;
-pub use self::server::PixelflutServerStream;
+pub use self::server::{PixelflutServer, PixelflutServerStream};
@@ -1,13 +1,27 @@
 //! Contains the sync server for pixelflut.
-use std::io::{BufRead, BufReader, Write};
-use std::net::TcpStream;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
 
-use crate::command::{Command, Response};
-use crate::error::PixelflutResult;
-use crate::Pixel;
+use crate::command::{
+    get_px_response, Command, CustomCommandHandler, PixelLookup, Response,
+    CUSTOM_COMMAND_MAX_LINE_LENGTH, DEFAULT_HELP_TEXT,
+};
+use crate::error::{PixelflutError, PixelflutErrorKind, PixelflutResult};
+use crate::error_policy::ErrorCounter;
+use crate::handler::PixelflutHandler;
+use crate::pixel::MAX_FORMATTED_PIXEL_SIZE_NEWLINE;
+use crate::pixel_buffer::{decode_binary_pixel, BINARY_PIXEL_SIZE};
+use crate::{Canvas, Color, Coordinate, ErrorPolicy, Pixel};
 
 /// Sync Pixelflut server connection.
 ///
+/// The stream can be used with any transport implementing [`Read`] and [`Write`],
+/// e.g. a `TcpStream`, a `UnixStream` or a serial port.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -26,49 +40,464 @@ use crate::Pixel;
 ///     Ok(())
 /// }
 /// ```
-pub struct PixelflutServerStream {
-    reader: BufReader<TcpStream>,
+pub struct PixelflutServerStream<S = TcpStream> {
+    reader: BufReader<S>,
     dimensions: (u32, u32),
+    pixel_lookup: Option<PixelLookup>,
+    offset: Coordinate,
+    help: Cow<'static, str>,
+    error_policy: ErrorPolicy,
+    errors: ErrorCounter,
+    custom_commands: HashMap<String, CustomCommandHandler>,
+    max_line_length: usize,
 }
 
-impl PixelflutServerStream {
-    /// Creates a new `PixelflutStream` from a `TcpStream`.
-    pub fn new(stream: TcpStream, dimensions: (u32, u32)) -> PixelflutServerStream {
+impl<S: Read + Write> PixelflutServerStream<S> {
+    /// Creates a new `PixelflutStream` from a stream, e.g. a `TcpStream`.
+    pub fn new(stream: S, dimensions: (u32, u32)) -> PixelflutServerStream<S> {
         PixelflutServerStream {
             reader: BufReader::new(stream),
             dimensions,
+            pixel_lookup: None,
+            offset: Coordinate::default(),
+            help: Cow::Borrowed(DEFAULT_HELP_TEXT),
+            error_policy: ErrorPolicy::default(),
+            errors: ErrorCounter::default(),
+            custom_commands: HashMap::new(),
+            max_line_length: MAX_FORMATTED_PIXEL_SIZE_NEWLINE,
+        }
+    }
+
+    /// Creates a new `PixelflutServerStream` that answers `HELP` with a custom text.
+    ///
+    /// The text may contain multiple lines.
+    pub fn with_help(
+        stream: S,
+        dimensions: (u32, u32),
+        help: impl Into<Cow<'static, str>>,
+    ) -> PixelflutServerStream<S> {
+        PixelflutServerStream {
+            help: help.into(),
+            ..PixelflutServerStream::new(stream, dimensions)
         }
     }
 
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    /// Returns the offset that is currently applied to received pixels.
+    ///
+    /// The offset is set by the client with an `OFFSET x y` command.
+    pub fn offset(&self) -> Coordinate {
+        self.offset
+    }
+
+    /// Sets the function used to answer `PX x y` requests.
+    ///
+    /// The function returns the current color at the coordinate
+    /// or `None` if the coordinate is outside of the canvas.
+    /// Without a lookup, `PX x y` requests are answered with an `ERROR`.
+    pub fn set_pixel_lookup(
+        &mut self,
+        lookup: impl Fn(Coordinate) -> Option<Color> + Send + Sync + 'static,
+    ) {
+        self.pixel_lookup = Some(Box::new(lookup));
+    }
+
+    /// Sets the policy deciding when invalid lines close the connection.
+    ///
+    /// By default, the first invalid line is returned as an error.
+    pub fn set_error_policy(&mut self, error_policy: ErrorPolicy) {
+        self.error_policy = error_policy;
+    }
+
+    /// Returns the number of invalid lines received so far.
+    pub fn error_count(&self) -> u64 {
+        self.errors.errors()
+    }
+
+    /// Registers a handler answering [`Command::Custom`] commands with the given verb.
+    ///
+    /// The handler gets the arguments of the command and pushes its answers to the `Vec`.
+    /// Errors are answered with an `ERROR` and counted by the error policy,
+    /// like custom commands without a handler.
+    /// The built-in commands can not be replaced.
+    ///
+    /// Registering a command raises the line limit to at least 1024 bytes,
+    /// see [`set_max_line_length`](Self::set_max_line_length).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pixelflut::sync::PixelflutServerStream;
+    /// use pixelflut::Response;
+    /// use std::net::TcpStream;
+    ///
+    /// fn handle_client(stream: TcpStream) -> pixelflut::PixelflutResult<()> {
+    ///     let mut stream = PixelflutServerStream::new(stream, (800, 600));
+    ///     stream.register_command("PING", |_args, responses| {
+    ///         responses.push(Response::Custom("PONG".into()));
+    ///         Ok(())
+    ///     });
+    ///     while let Some(_pixel) = stream.read_pixel()? {}
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn register_command(
+        &mut self,
+        verb: impl Into<String>,
+        handler: impl FnMut(&[String], &mut Vec<Response>) -> PixelflutResult<()> + Send + 'static,
+    ) {
+        self.custom_commands.insert(verb.into(), Box::new(handler));
+        self.allow_custom_commands();
+    }
+
+    /// Raises the line limit, so it fits custom commands.
+    fn allow_custom_commands(&mut self) {
+        self.max_line_length = self.max_line_length.max(CUSTOM_COMMAND_MAX_LINE_LENGTH);
+    }
+
+    /// Sets the maximum length of a line, including the newline.
+    ///
+    /// The default fits the longest `PX` command,
+    /// registered custom commands and `run_handler` raise it to 1024 bytes.
+    /// Custom commands with longer arguments need a larger limit.
+    /// Longer lines end the connection with an error.
+    pub fn set_max_line_length(&mut self, max_line_length: usize) {
+        self.max_line_length = max_line_length;
+    }
+
     /// Sends a `Response` to the client.
     fn send_response(&mut self, response: &Response) -> PixelflutResult<()> {
-        self.reader
-            .get_mut()
-            .write_fmt(format_args!("{}\n", response))?;
+        let stream = self.reader.get_mut();
+        stream.write_fmt(format_args!("{}\n", response))?;
+        stream.flush()?;
         Ok(())
     }
 
     /// Reads a `Command` from the stream.
+    ///
+    /// Returns `Ok(None)` on a clean end of the stream.
+    /// Invalid lines are answered with an `ERROR`
+    /// and skipped, as long as the error policy allows it.
     fn read_command(&mut self) -> PixelflutResult<Option<Command>> {
-        let mut line = String::new();
-        let _len = match self.reader.read_line(&mut line) {
-            Ok(n) => n,
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-            Err(e) => return Err(e.into()),
-        };
-        Ok(Some(line[0..line.len()].parse()?))
+        loop {
+            match self.read_line_command() {
+                Ok(Some(command)) => {
+                    self.errors.record_line();
+                    return Ok(Some(command));
+                }
+                Err(err) if err.kind() != PixelflutErrorKind::Io => {
+                    self.errors.record_line();
+                    self.handle_invalid_line(err)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Answers an invalid line with an `ERROR`.
+    ///
+    /// Returns the error, if the error policy does not allow to skip the line.
+    fn handle_invalid_line(&mut self, err: PixelflutError) -> PixelflutResult<()> {
+        self.send_response(&Response::Error(err.to_string().into()))?;
+        if self.errors.record_error(&self.error_policy) {
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reads a single line or binary pixel from the stream and parses it.
+    ///
+    /// Text lines and binary pixels can be mixed.
+    /// Invalid lines are consumed from the stream.
+    fn read_line_command(&mut self) -> PixelflutResult<Option<Command>> {
+        let mut line = Vec::new();
+        let available = self.reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(None);
+        }
+        if available[0] == b'P' && available.get(1).map_or(true, |&b| b == b'B') {
+            // binary pixels may contain newlines, so the prefix decides about the framing
+            line.resize(2, 0);
+            self.reader.read_exact(&mut line)?;
+            if line == b"PB" {
+                let mut bytes = [0u8; BINARY_PIXEL_SIZE];
+                bytes[..2].copy_from_slice(&line);
+                self.reader.read_exact(&mut bytes[2..])?;
+                return Ok(Some(Command::Px(decode_binary_pixel(&bytes))));
+            }
+        }
+        if line.last() != Some(&b'\n') {
+            // read at most one byte more than allowed, to detect lines that are to long
+            let limit = self.max_line_length + 1 - line.len();
+            (&mut self.reader)
+                .take(limit as u64)
+                .read_until(b'\n', &mut line)?;
+            if line.last() != Some(&b'\n') {
+                return Err(if line.len() > self.max_line_length {
+                    PixelflutErrorKind::Io.with_description("line is to long")
+                } else {
+                    PixelflutErrorKind::Io.with_description("Unexpected end of stream")
+                });
+            }
+        }
+        Command::from_bytes_or_custom(&line).map(Some)
+    }
+
+    /// Returns `true`, if a complete command is in the read buffer,
+    /// so it can be read without blocking.
+    fn has_buffered_command(&self) -> bool {
+        let buffer = self.reader.buffer();
+        if buffer.starts_with(b"PB") {
+            buffer.len() >= BINARY_PIXEL_SIZE
+        } else {
+            memchr::memchr(b'\n', buffer).is_some()
+        }
+    }
+
+    /// Handles a command and returns the pixel, if the command sets a pixel.
+    ///
+    /// All other commands are answered directly.
+    fn handle_command(&mut self, command: Command) -> PixelflutResult<Option<Pixel>> {
+        self.handle_command_with(command, |_, _| None)
+    }
+
+    /// Like [`handle_command`](Self::handle_command),
+    /// but custom commands without a registered handler are passed to `unknown`.
+    fn handle_command_with(
+        &mut self,
+        command: Command,
+        mut unknown: impl FnMut(&str, &[String]) -> Option<Response>,
+    ) -> PixelflutResult<Option<Pixel>> {
+        match command {
+            Command::Px(pixel) => {
+                // pixels translated beyond the coordinate range are dropped
+                return Ok(pixel
+                    .position
+                    .checked_add(self.offset)
+                    .map(|position| Pixel::new(position, pixel.color)));
+            }
+            Command::Size => self.send_response(&Response::Size {
+                w: self.dimensions.0,
+                h: self.dimensions.1,
+            })?,
+            Command::GetPx(position) => {
+                let response = get_px_response(self.pixel_lookup.as_ref(), position, self.offset);
+                self.send_response(&response)?
+            }
+            Command::Offset(offset) => self.offset = offset,
+            Command::Help => self.send_response(&Response::Help(self.help.clone()))?,
+            Command::Custom(custom) => {
+                let mut responses = Vec::new();
+                let result = match self.custom_commands.get_mut(&custom.verb) {
+                    Some(handler) => handler(&custom.args, &mut responses),
+                    None => match unknown(&custom.verb, &custom.args) {
+                        Some(response) => {
+                            responses.push(response);
+                            Ok(())
+                        }
+                        None => Err(PixelflutErrorKind::InvalidCommand.into()),
+                    },
+                };
+                match result {
+                    Ok(()) => {
+                        for response in &responses {
+                            self.send_response(response)?;
+                        }
+                    }
+                    Err(err) => self.handle_invalid_line(err)?,
+                }
+            }
+        }
+        Ok(None)
     }
 
     pub fn read_pixel(&mut self) -> PixelflutResult<Option<Pixel>> {
+        while let Some(command) = self.read_command()? {
+            if let Some(pixel) = self.handle_command(command)? {
+                return Ok(Some(pixel));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads all pixels that are currently buffered and passes them to a callback.
+    ///
+    /// Blocks until at least one command is received.
+    /// Other commands like `SIZE` are answered along the way.
+    ///
+    /// # Returns
+    /// The number of pixels passed to the callback, which might be zero,
+    /// if only other commands were received,
+    /// or `None` at the end of the stream.
+    pub fn read_pixels_with(&mut self, mut f: impl FnMut(Pixel)) -> PixelflutResult<Option<usize>> {
+        let mut pixels = 0;
+        let mut command = match self.read_command()? {
+            Some(command) => command,
+            None => return Ok(None),
+        };
         loop {
-            match self.read_command()? {
-                Some(Command::Px(pixel)) => return Ok(Some(pixel)),
-                Some(Command::Size) => self.send_response(&Response::Size {
-                    w: self.dimensions.0,
-                    h: self.dimensions.1,
-                })?,
-                None => return Ok(None),
+            if let Some(pixel) = self.handle_command(command)? {
+                f(pixel);
+                pixels += 1;
+            }
+            if !self.has_buffered_command() {
+                return Ok(Some(pixels));
+            }
+            command = match self.read_command()? {
+                Some(command) => command,
+                None => return Ok(Some(pixels)),
+            };
+        }
+    }
+
+    /// Reads all pixels that are currently buffered and appends them to `pixels`.
+    ///
+    /// See [`read_pixels_with`](Self::read_pixels_with).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use pixelflut::sync::PixelflutServerStream;
+    /// use std::net::TcpStream;
+    ///
+    /// fn handle_client(stream: TcpStream) -> pixelflut::PixelflutResult<()> {
+    ///     let mut stream = PixelflutServerStream::new(stream, (800, 600));
+    ///     let mut pixels = Vec::new();
+    ///     while let Some(_count) = stream.read_pixels(&mut pixels)? {
+    ///         for pixel in pixels.drain(..) {
+    ///             println!("{}", pixel);
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_pixels(&mut self, pixels: &mut Vec<Pixel>) -> PixelflutResult<Option<usize>> {
+        self.read_pixels_with(|pixel| pixels.push(pixel))
+    }
+
+    /// Passes all received commands to a [`PixelflutHandler`] until the end of the stream.
+    ///
+    /// Commands the handler does not answer itself are handled like in
+    /// [`read_pixel`](Self::read_pixel) and pixels are passed to
+    /// [`on_pixel`](PixelflutHandler::on_pixel).
+    /// An error is returned after [`on_disconnect`](PixelflutHandler::on_disconnect) was called.
+    ///
+    /// The handler may answer custom commands in
+    /// [`on_unknown_command`](PixelflutHandler::on_unknown_command),
+    /// so the line limit is raised to at least 1024 bytes, like by `register_command`.
+    pub fn run_handler(&mut self, handler: &mut impl PixelflutHandler) -> PixelflutResult<()> {
+        self.allow_custom_commands();
+        handler.on_connect();
+        let result = self.dispatch(handler);
+        handler.on_disconnect(result.as_ref().err());
+        result
+    }
+
+    fn dispatch(&mut self, handler: &mut impl PixelflutHandler) -> PixelflutResult<()> {
+        while let Some(command) = self.read_command()? {
+            match handler.on_command(&command) {
+                Some(response) => self.send_response(&response)?,
+                None => {
+                    let unknown =
+                        |verb: &str, args: &[String]| handler.on_unknown_command(verb, args);
+                    if let Some(pixel) = self.handle_command_with(command, unknown)? {
+                        handler.on_pixel(pixel);
+                    }
+                }
             }
         }
+        Ok(())
+    }
+}
+
+/// Sync Pixelflut server drawing on a shared [`Canvas`].
+///
+/// Every connection is handled in its own thread.
+/// `SIZE` is answered with the dimensions of the canvas
+/// and `PX x y` with the current color of the pixel.
+///
+/// # Examples
+///
+/// ```no_run
+/// use pixelflut::sync::PixelflutServer;
+/// use pixelflut::Canvas;
+/// use std::sync::Arc;
+///
+/// fn main() -> pixelflut::PixelflutResult<()> {
+///     let canvas = Arc::new(Canvas::new(800, 600));
+///     let server = PixelflutServer::bind("127.0.0.1:1337", canvas)?;
+///     server.run()
+/// }
+/// ```
+pub struct PixelflutServer {
+    listener: TcpListener,
+    canvas: Arc<Canvas>,
+    error_policy: ErrorPolicy,
+}
+
+impl PixelflutServer {
+    /// Binds a new server to the given address.
+    pub fn bind(addr: impl ToSocketAddrs, canvas: Arc<Canvas>) -> PixelflutResult<PixelflutServer> {
+        Ok(PixelflutServer {
+            listener: TcpListener::bind(addr)?,
+            canvas,
+            error_policy: ErrorPolicy::default(),
+        })
+    }
+
+    /// Returns the address the server is listening on.
+    pub fn local_addr(&self) -> PixelflutResult<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Returns the canvas the server draws on.
+    pub fn canvas(&self) -> &Arc<Canvas> {
+        &self.canvas
+    }
+
+    /// Sets the error policy used for every new connection.
+    ///
+    /// See [`PixelflutServerStream::set_error_policy`].
+    pub fn set_error_policy(&mut self, error_policy: ErrorPolicy) {
+        self.error_policy = error_policy;
+    }
+
+    /// Accepts connections until an error occurs while accepting.
+    ///
+    /// Errors in a single connection only close that connection.
+    pub fn run(self) -> PixelflutResult<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let canvas = self.canvas.clone();
+            let error_policy = self.error_policy;
+            thread::spawn(move || {
+                let _ = handle_connection(stream, canvas, error_policy);
+            });
+        }
+        Ok(())
     }
 }
+
+fn handle_connection(
+    stream: TcpStream,
+    canvas: Arc<Canvas>,
+    error_policy: ErrorPolicy,
+) -> PixelflutResult<()> {
+    let mut stream = PixelflutServerStream::new(stream, canvas.dimensions());
+    stream.set_error_policy(error_policy);
+    let lookup_canvas = canvas.clone();
+    stream.set_pixel_lookup(move |position| lookup_canvas.get(position));
+    let set_pixel = |pixel: Pixel| canvas.set(&pixel);
+    while let Some(_count) = stream.read_pixels_with(set_pixel)? {}
+    Ok(())
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "smol-rt")]
+
+use futures::{stream, SinkExt, StreamExt, TryStreamExt};
+use pixelflut::async_smol::{PixelflutClient, PixelflutServer, PixelflutServerStream};
+use pixelflut::{Canvas, Color, ErrorPolicy, Pixel, PixelEncoding, PixelflutErrorKind};
+use smol::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use smol::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// Connects a client and a server stream over a local TCP connection.
+async fn connect(dimensions: (u32, u32)) -> (PixelflutClient, PixelflutServerStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = PixelflutClient::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _addr) = listener.accept().await.unwrap();
+    (client, PixelflutServerStream::new(server, dimensions))
+}
+
+/// Reads pixels until the client side is closed.
+async fn collect_pixels(mut server: PixelflutServerStream) -> Vec<Pixel> {
+    let mut pixels = Vec::new();
+    while let Some(pixel) = server.read_pixel().await.unwrap() {
+        pixels.push(pixel);
+    }
+    pixels
+}
+
+#[test]
+fn set_pixels() {
+    smol::block_on(async {
+        let (mut client, server) = connect((800, 600)).await;
+        let server = smol::spawn(collect_pixels(server));
+
+        client.set(1, 2, (255, 0, 0)).await.unwrap();
+        client.set_encoding(PixelEncoding::Binary);
+        client.set(3, 4, Color::rgba(0, 255, 0, 128)).await.unwrap();
+        client.flush().await.unwrap();
+        drop(client);
+
+        assert_eq!(
+            server.await,
+            vec![
+                Pixel::from(((1, 2), (255, 0, 0))),
+                Pixel::new((3, 4).into(), Color::rgba(0, 255, 0, 128)),
+            ]
+        );
+    });
+}
+
+#[test]
+fn requests() {
+    smol::block_on(async {
+        let (mut client, mut server) = connect((800, 600)).await;
+        server.set_pixel_lookup(|position| Some(Color::rgb(position.x as u8, 0, 0)));
+        let server = smol::spawn(collect_pixels(server));
+
+        assert_eq!(client.dimensions().await.unwrap(), (800, 600));
+        assert_eq!(client.get(3, 4).await.unwrap(), Color::rgb(3, 0, 0));
+        assert!(client.help().await.unwrap().contains("PX"));
+        drop(client);
+        assert!(server.await.is_empty());
+    });
+}
+
+#[test]
+fn skip_invalid_lines() {
+    smol::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _addr) = listener.accept().await.unwrap();
+        let mut server = PixelflutServerStream::new(server, (800, 600));
+        server.set_error_policy(ErrorPolicy::skip().with_max_errors(1));
+
+        client
+            .write_all(b"FOO\nPX 1 2 ff0000\nPX 1\n")
+            .await
+            .unwrap();
+        assert_eq!(
+            server.next().await.unwrap().unwrap(),
+            Pixel::from(((1, 2), (255, 0, 0)))
+        );
+        assert_eq!(
+            server.next().await.unwrap().unwrap_err().kind(),
+            PixelflutErrorKind::WrongNumberOfArguments
+        );
+
+        let mut lines = BufReader::new(client).lines();
+        assert_eq!(
+            lines.next().await.unwrap().unwrap(),
+            "ERROR invalid command"
+        );
+        assert!(lines.next().await.unwrap().unwrap().starts_with("ERROR "));
+    });
+}
+
+#[test]
+fn stream_and_sink() {
+    smol::block_on(async {
+        let (mut client, server) = connect((800, 600)).await;
+        let server = smol::spawn(server.try_collect::<Vec<Pixel>>());
+
+        let expected: Vec<Pixel> = (0..10_000)
+            .map(|i| Pixel::from(((i % 800, i / 800), (0, 0, 255))))
+            .collect();
+        let mut pixels = stream::iter(expected.clone()).map(Ok);
+        client.send_all(&mut pixels).await.unwrap();
+        client.close().await.unwrap();
+
+        assert_eq!(server.await.unwrap(), expected);
+    });
+}
+
+#[test]
+fn server_on_async_std() {
+    async_std::task::block_on(async {
+        let canvas = Arc::new(Canvas::new(800, 600));
+        let server = PixelflutServer::bind("127.0.0.1:0", canvas.clone())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        async_std::task::spawn(server.run());
+
+        // the streams also work with the async-std networking types
+        let stream = async_std::net::TcpStream::connect(addr).await.unwrap();
+        let mut client = PixelflutClient::new(stream);
+        client.set(1, 2, (255, 0, 0)).await.unwrap();
+        assert_eq!(client.get(1, 2).await.unwrap(), Color::rgb(255, 0, 0));
+        assert_eq!(canvas.get((1, 2).into()), Some(Color::rgb(255, 0, 0)));
+    });
+}
@@ -0,0 +1,338 @@
+#![cfg(feature = "tokio-rt")]
+
+use futures::{stream, SinkExt, StreamExt, TryStreamExt};
+use pixelflut::async_tokio::{PixelflutClient, PixelflutServerStream};
+use pixelflut::{
+    Color, Command, ErrorPolicy, Painter, Pixel, PixelEncoding, PixelflutErrorKind,
+    PixelflutHandler, Rect, Response,
+};
+use tokio::io::{duplex, AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+
+fn connect(
+    dimensions: (u32, u32),
+) -> (
+    PixelflutClient<DuplexStream>,
+    PixelflutServerStream<DuplexStream>,
+) {
+    let (client, server) = duplex(64 * 1024);
+    (
+        PixelflutClient::new(client),
+        PixelflutServerStream::new(server, dimensions),
+    )
+}
+
+/// Reads pixels until the client side is closed.
+async fn collect_pixels(mut server: PixelflutServerStream<DuplexStream>) -> Vec<Pixel> {
+    let mut pixels = Vec::new();
+    while let Some(pixel) = server.read_pixel().await.unwrap() {
+        pixels.push(pixel);
+    }
+    pixels
+}
+
+#[tokio::test]
+async fn set_pixels() {
+    let (mut client, server) = connect((800, 600));
+    let server = tokio::spawn(collect_pixels(server));
+
+    client.set(1, 2, (255, 0, 0)).await.unwrap();
+    client.set(3, 4, Color::rgba(0, 255, 0, 128)).await.unwrap();
+    client.flush().await.unwrap();
+    drop(client);
+
+    assert_eq!(
+        server.await.unwrap(),
+        vec![
+            Pixel::from(((1, 2), (255, 0, 0))),
+            Pixel::new((3, 4).into(), Color::rgba(0, 255, 0, 128)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn draw_shapes() {
+    let (mut client, server) = connect((4, 4));
+    let server = tokio::spawn(collect_pixels(server));
+
+    let painter = Painter::new(client.dimensions().await.unwrap());
+    client
+        .set_pixels(painter.stroke_rect(Rect::new(2, 2, 5, 5), (255, 0, 0)))
+        .await
+        .unwrap();
+    client.flush().await.unwrap();
+    drop(client);
+
+    assert_eq!(
+        server.await.unwrap(),
+        vec![
+            Pixel::from(((2, 2), (255, 0, 0))),
+            Pixel::from(((3, 2), (255, 0, 0))),
+            Pixel::from(((2, 3), (255, 0, 0))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dimensions() {
+    let (mut client, server) = connect((800, 600));
+    let server = tokio::spawn(collect_pixels(server));
+
+    assert_eq!(client.dimensions().await.unwrap(), (800, 600));
+    drop(client);
+    assert!(server.await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn get_pixel_with_offset() {
+    let (mut client, mut server) = connect((800, 600));
+    server.set_pixel_lookup(|position| {
+        if position.x < 800 && position.y < 600 {
+            Some(Color::rgb(position.x as u8, position.y as u8, 0))
+        } else {
+            None
+        }
+    });
+    let server = tokio::spawn(collect_pixels(server));
+
+    assert_eq!(client.get(1, 2).await.unwrap(), Color::rgb(1, 2, 0));
+    assert_eq!(
+        client.get(800, 0).await.unwrap_err().kind(),
+        PixelflutErrorKind::ServerError
+    );
+
+    client.set_offset(10, 20).await.unwrap();
+    assert_eq!(client.get(1, 2).await.unwrap(), Color::rgb(11, 22, 0));
+    client.set(1, 2, (0, 0, 0)).await.unwrap();
+    client.flush().await.unwrap();
+    drop(client);
+
+    assert_eq!(
+        server.await.unwrap(),
+        vec![Pixel::from(((11, 22), (0, 0, 0)))]
+    );
+}
+
+#[tokio::test]
+async fn help() {
+    let (client, server) = duplex(1024);
+    let mut client = PixelflutClient::new(client);
+    let server = PixelflutServerStream::with_help(server, (800, 600), "line one\nline two");
+    let server = tokio::spawn(collect_pixels(server));
+
+    assert_eq!(client.help().await.unwrap(), "line one\nline two");
+    drop(client);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn binary_and_text_mixed() {
+    let (mut client, mut server) = connect((800, 600));
+    let server = tokio::spawn(async move {
+        let mut pixels = Vec::new();
+        while server.read_pixels(&mut pixels).await.unwrap().is_some() {}
+        pixels
+    });
+
+    let mut expected = Vec::new();
+    for i in 0..1000 {
+        client.set_encoding(if i % 3 == 0 {
+            PixelEncoding::Binary
+        } else {
+            PixelEncoding::Text
+        });
+        // 10 is a newline in the binary encoding
+        client.set(i, 10, (10, 10, 10)).await.unwrap();
+        expected.push(Pixel::from(((i, 10), (10, 10, 10))));
+    }
+    client.flush().await.unwrap();
+    drop(client);
+
+    assert_eq!(server.await.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn invalid_command() {
+    let (client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    let mut client = BufReader::new(client);
+
+    client.write_all(b"FOO\n").await.unwrap();
+    let err = server.read_pixel().await.unwrap_err();
+    assert_eq!(err.kind(), PixelflutErrorKind::InvalidCommand);
+
+    let mut line = String::new();
+    client.read_line(&mut line).await.unwrap();
+    assert!(line.starts_with("ERROR "));
+}
+
+#[tokio::test]
+async fn end_of_stream() {
+    let (client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    drop(client);
+    assert_eq!(server.read_pixel().await.unwrap(), None);
+
+    let (mut client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    client.write_all(b"PX 1 2").await.unwrap();
+    drop(client);
+    assert_eq!(
+        server.read_pixel().await.unwrap_err().kind(),
+        PixelflutErrorKind::Io
+    );
+}
+
+#[tokio::test]
+async fn skip_invalid_lines() {
+    let (client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    server.set_error_policy(ErrorPolicy::skip());
+    let mut client = BufReader::new(client);
+
+    client
+        .write_all(b"FOO\nPX 1 2 ff0000\nPX 1 2 zz0000\nPX 3 4 00ff00\n")
+        .await
+        .unwrap();
+    assert_eq!(
+        server.read_pixel().await.unwrap(),
+        Some(Pixel::from(((1, 2), (255, 0, 0))))
+    );
+    assert_eq!(
+        server.read_pixel().await.unwrap(),
+        Some(Pixel::from(((3, 4), (0, 255, 0))))
+    );
+    assert_eq!(server.error_count(), 2);
+
+    for _ in 0..2 {
+        let mut line = String::new();
+        client.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("ERROR "));
+    }
+}
+
+/// Handler collecting pixels and answering `PING`.
+#[derive(Default)]
+struct PingHandler {
+    connected: bool,
+    pixels: Vec<Pixel>,
+    commands: usize,
+    disconnected: bool,
+}
+
+impl PixelflutHandler for PingHandler {
+    fn on_connect(&mut self) {
+        self.connected = true;
+    }
+
+    fn on_pixel(&mut self, pixel: Pixel) {
+        self.pixels.push(pixel);
+    }
+
+    fn on_command(&mut self, _command: &Command) -> Option<Response> {
+        self.commands += 1;
+        None
+    }
+
+    fn on_unknown_command(&mut self, verb: &str, _args: &[String]) -> Option<Response> {
+        match verb {
+            "PING" => Some(Response::Custom("PONG".into())),
+            _ => None,
+        }
+    }
+
+    fn on_disconnect(&mut self, error: Option<&pixelflut::PixelflutError>) {
+        assert!(error.is_none());
+        self.disconnected = true;
+    }
+}
+
+#[tokio::test]
+async fn run_handler() {
+    let (client, mut server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let mut server = PixelflutServerStream::new(&mut server, (800, 600));
+        let mut handler = PingHandler::default();
+        server.run_handler(&mut handler).await.unwrap();
+        handler
+    });
+    let mut client = BufReader::new(client);
+
+    client
+        .write_all(b"PX 1 2 ff0000\nSIZE\nPING the quick brown fox jumps over the lazy dog\n")
+        .await
+        .unwrap();
+    let mut lines = String::new();
+    for _ in 0..2 {
+        client.read_line(&mut lines).await.unwrap();
+    }
+    assert_eq!(lines, "SIZE 800 600\nPONG\n");
+    drop(client);
+
+    let handler = server.await.unwrap();
+    assert!(handler.connected);
+    assert!(handler.disconnected);
+    assert_eq!(handler.commands, 3);
+    assert_eq!(handler.pixels, vec![Pixel::from(((1, 2), (255, 0, 0)))]);
+}
+
+#[tokio::test]
+async fn custom_commands() {
+    let (client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    server.register_command("STATS", |args, responses| {
+        responses.push(Response::Custom(format!("STATS {}", args.len()).into()));
+        Ok(())
+    });
+    let mut client = BufReader::new(client);
+
+    client
+        .write_all(b"STATS a b c d e f g h i j k l m n o p\nPX 1 2 ff0000\n")
+        .await
+        .unwrap();
+    assert_eq!(
+        server.read_pixel().await.unwrap(),
+        Some(Pixel::from(((1, 2), (255, 0, 0))))
+    );
+    let mut line = String::new();
+    client.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "STATS 16\n");
+
+    client.write_all(b"CLEAR\n").await.unwrap();
+    assert_eq!(
+        server.read_pixel().await.unwrap_err().kind(),
+        PixelflutErrorKind::InvalidCommand
+    );
+}
+
+#[tokio::test]
+async fn stream_and_sink() {
+    let (mut client, server) = connect((800, 600));
+    let server = tokio::spawn(server.try_collect::<Vec<Pixel>>());
+
+    let expected: Vec<Pixel> = (0..1000)
+        .map(|i| Pixel::from(((i % 800, i / 800), (255, 0, 0))))
+        .collect();
+    let mut pixels = stream::iter(expected.clone()).map(Ok);
+    client.send_all(&mut pixels).await.unwrap();
+    assert_eq!(client.dimensions().await.unwrap(), (800, 600));
+    client.close().await.unwrap();
+
+    assert_eq!(server.await.unwrap().unwrap(), expected);
+}
+
+#[tokio::test]
+async fn stream_error() {
+    let (client, server) = duplex(1024);
+    let mut server = PixelflutServerStream::new(server, (800, 600));
+    let mut client = BufReader::new(client);
+
+    client.write_all(b"PX 1 2 ff0000\nFOO\n").await.unwrap();
+    assert!(server.next().await.unwrap().is_ok());
+    assert_eq!(
+        server.next().await.unwrap().unwrap_err().kind(),
+        PixelflutErrorKind::InvalidCommand
+    );
+    let mut line = String::new();
+    client.read_line(&mut line).await.unwrap();
+    assert_eq!(line, "ERROR invalid command\n");
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "serde")]
+
+use pixelflut::{Color, Command, Coordinate, CustomCommand, Pixel, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Serializes a value as JSON and MessagePack and checks that it is read back unchanged.
+fn assert_roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(
+        &serde_json::from_str::<T>(&json).unwrap(),
+        value,
+        "{}",
+        json
+    );
+
+    let msgpack = rmp_serde::to_vec(value).unwrap();
+    assert_eq!(&rmp_serde::from_slice::<T>(&msgpack).unwrap(), value);
+}
+
+#[test]
+fn color_as_hex() {
+    assert_eq!(
+        serde_json::to_string(&Color::rgb(255, 0, 16)).unwrap(),
+        r#""ff0010""#
+    );
+    assert_eq!(
+        serde_json::to_string(&Color::rgba(255, 0, 16, 0)).unwrap(),
+        r#""ff001000""#
+    );
+    assert_eq!(
+        serde_json::from_str::<Color>(r#""ABCDEF80""#).unwrap(),
+        Color::rgba(0xab, 0xcd, 0xef, 0x80)
+    );
+    assert!(serde_json::from_str::<Color>(r#""fff""#).is_err());
+    assert!(serde_json::from_str::<Color>("16711680").is_err());
+}
+
+#[test]
+fn pixel_as_json() {
+    let pixel = Pixel::new(Coordinate::new(1, 2), Color::rgba(255, 0, 0, 128));
+    assert_eq!(
+        serde_json::to_string(&pixel).unwrap(),
+        r#"{"position":{"x":1,"y":2},"color":"ff000080"}"#
+    );
+}
+
+#[test]
+fn roundtrip_pixels() {
+    // the alpha channel is kept, also if it is fully opaque
+    assert_roundtrip(&Color::rgb(1, 2, 3));
+    assert_roundtrip(&Color::rgba(1, 2, 3, 255));
+    assert_roundtrip(&Coordinate::new(u32::MAX, 0));
+    assert_roundtrip(&Pixel::from(((12, 34), (0, 255, 0, 0))));
+    assert_roundtrip(&vec![
+        Pixel::from(((0, 0), (0, 0, 0))),
+        Pixel::from(((1, 0), (255, 255, 255))),
+    ]);
+}
+
+#[test]
+fn roundtrip_commands() {
+    for command in [
+        Command::Px(Pixel::from(((1, 2), (3, 4, 5, 6)))),
+        Command::GetPx(Coordinate::new(7, 8)),
+        Command::Offset(Coordinate::new(9, 10)),
+        Command::Size,
+        Command::Help,
+        Command::Custom(Box::new(CustomCommand::new(
+            "TEXT".to_string(),
+            vec!["1".to_string(), "2".to_string(), "hello".to_string()],
+        ))),
+    ] {
+        assert_roundtrip(&command);
+    }
+}
+
+#[test]
+fn roundtrip_responses() {
+    for response in [
+        Response::Size { w: 800, h: 600 },
+        Response::Px(Pixel::from(((1, 2), (3, 4, 5)))),
+        Response::Help("first line\nsecond line".into()),
+        Response::Error("out of range".into()),
+        Response::Custom("STATS 12 pixels".into()),
+    ] {
+        assert_roundtrip(&response);
+    }
+}
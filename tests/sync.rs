@@ -0,0 +1,367 @@
+#![cfg(feature = "sync")]
+
+use pixelflut::sync::{PixelflutClient, PixelflutServerStream};
+use pixelflut::{
+    Color, Command, Coordinate, ErrorPolicy, Font, Painter, Pixel, PixelBuffer, PixelEncoding,
+    PixelflutError, PixelflutErrorKind, PixelflutHandler, Response, Text,
+};
+use std::io::{self, Cursor, Read, Write};
+
+/// In-memory stream that reads from one cursor and writes into another.
+struct CursorPair {
+    input: Cursor<Vec<u8>>,
+    output: Cursor<Vec<u8>>,
+}
+
+impl CursorPair {
+    fn new(input: &[u8]) -> CursorPair {
+        CursorPair {
+            input: Cursor::new(input.to_vec()),
+            output: Cursor::new(Vec::new()),
+        }
+    }
+
+    fn output(&self) -> &[u8] {
+        self.output.get_ref()
+    }
+}
+
+impl Read for CursorPair {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for CursorPair {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn client_set_pixels() {
+    let mut client = PixelflutClient::new(CursorPair::new(b""));
+    client.set(1, 2, (255, 0, 0)).unwrap();
+    client.set_encoding(PixelEncoding::Binary);
+    client.set(3, 4, Color::rgba(0, 255, 0, 128)).unwrap();
+    client.flush().unwrap();
+    assert_eq!(
+        client.get_ref().output(),
+        b"PX 1 2 ff0000\nPB\x03\x00\x04\x00\x00\xff\x00\x80"
+    );
+}
+
+#[test]
+fn client_flushes_on_drop() {
+    let mut stream = CursorPair::new(b"");
+    let mut client = PixelflutClient::new(&mut stream);
+    client.set(1, 2, (255, 0, 0)).unwrap();
+    drop(client);
+    assert_eq!(stream.output(), b"PX 1 2 ff0000\n");
+}
+
+#[test]
+fn client_write_buffer() {
+    let buffer: PixelBuffer = vec![((3, 4), (0, 0, 255))].into_iter().collect();
+    let mut client = PixelflutClient::new(CursorPair::new(b""));
+    client.set(1, 2, (255, 0, 0)).unwrap();
+    client.write_buffer(&buffer).unwrap();
+    client.flush().unwrap();
+    assert_eq!(client.get_ref().output(), b"PX 1 2 ff0000\nPX 3 4 0000ff\n");
+}
+
+#[test]
+fn client_draw_shapes() {
+    let mut client = PixelflutClient::new(CursorPair::new(b"SIZE 3 2\n"));
+    let painter = Painter::new(client.dimensions().unwrap());
+    client
+        .set_pixels(painter.line(Coordinate::new(1, 1), Coordinate::new(4, 1), (255, 0, 0)))
+        .unwrap();
+    client.flush().unwrap();
+    assert_eq!(
+        client.get_ref().output(),
+        b"SIZE\nPX 1 1 ff0000\nPX 2 1 ff0000\n"
+    );
+}
+
+#[test]
+fn client_draw_text() {
+    let mut client = PixelflutClient::new(CursorPair::new(b"SIZE 2 6\n"));
+    let painter = Painter::new(client.dimensions().unwrap());
+    let font = Font::builtin();
+    let text = Text::new(&font, ".").with_color((0, 0, 255));
+    client.set_pixels(painter.text(&text)).unwrap();
+    client.flush().unwrap();
+    assert_eq!(client.get_ref().output(), b"SIZE\nPX 1 5 0000ff\n");
+}
+
+#[test]
+fn client_requests() {
+    let mut client = PixelflutClient::new(CursorPair::new(
+        b"SIZE 800 600\nPX 1 2 112233\nHELP first\nHELP second\nHELP\nERROR out of range\n",
+    ));
+    assert_eq!(client.dimensions().unwrap(), (800, 600));
+    assert_eq!(client.get(1, 2).unwrap(), Color::rgb(0x11, 0x22, 0x33));
+    assert_eq!(client.help().unwrap(), "first\nsecond");
+    assert_eq!(
+        client.get(900, 2).unwrap_err().kind(),
+        PixelflutErrorKind::ServerError
+    );
+    assert_eq!(client.get_ref().output(), b"SIZE\nPX 1 2\nHELP\nPX 900 2\n");
+}
+
+#[test]
+fn server_read_pixels() {
+    let mut input = b"PX 1 2 ff0000\nSIZE\nOFFSET 10 20\n".to_vec();
+    input.extend_from_slice(b"PB\x0a\x00\x0a\x00\x0a\x0a\x0a\xff");
+    input.extend_from_slice(b"PX 3 4 00ff0080\nPX 3 4\n");
+    let mut server = PixelflutServerStream::new(CursorPair::new(&input), (800, 600));
+    server.set_pixel_lookup(|position| Some(Color::rgb(position.x as u8, position.y as u8, 0)));
+
+    assert_eq!(
+        server.read_pixel().unwrap(),
+        Some(Pixel::from(((1, 2), (255, 0, 0))))
+    );
+    let mut pixels = Vec::new();
+    assert_eq!(server.read_pixels(&mut pixels).unwrap(), Some(2));
+    assert_eq!(
+        pixels,
+        vec![
+            Pixel::from(((20, 30), (10, 10, 10))),
+            Pixel::new((13, 24).into(), Color::rgba(0, 255, 0, 128)),
+        ]
+    );
+    assert_eq!(server.offset(), (10, 20).into());
+    assert_eq!(server.get_ref().output(), b"SIZE 800 600\nPX 3 4 0d1800\n");
+}
+
+#[test]
+fn server_offset_overflow() {
+    let mut server = PixelflutServerStream::new(
+        CursorPair::new(b"OFFSET 4294967295 0\nPX 1 0 ff0000\nPX 0 0 00ff00\nPX 1 0\n"),
+        (800, 600),
+    );
+    server.set_pixel_lookup(|_position| Some(Color::rgb(0, 0, 0)));
+
+    let mut pixels = Vec::new();
+    while server.read_pixels(&mut pixels).unwrap().is_some() {}
+    assert_eq!(pixels, vec![Pixel::from(((u32::MAX, 0), (0, 255, 0)))]);
+    assert_eq!(
+        server.get_ref().output(),
+        b"ERROR coordinate out of range\n"
+    );
+}
+
+#[test]
+fn server_help() {
+    let mut server =
+        PixelflutServerStream::with_help(CursorPair::new(b"HELP\nPX 0 0 000000\n"), (8, 6), "a\nb");
+    assert!(server.read_pixel().unwrap().is_some());
+    assert_eq!(server.get_ref().output(), b"HELP a\nHELP b\nHELP\n");
+}
+
+#[test]
+fn server_end_of_stream() {
+    let mut server = PixelflutServerStream::new(CursorPair::new(b""), (800, 600));
+    assert_eq!(server.read_pixel().unwrap(), None);
+
+    let mut server = PixelflutServerStream::new(CursorPair::new(b"PX 1 2 ff0000\n"), (800, 600));
+    assert!(server.read_pixel().unwrap().is_some());
+    assert_eq!(server.read_pixel().unwrap(), None);
+    assert_eq!(server.read_pixels(&mut Vec::new()).unwrap(), None);
+}
+
+#[test]
+fn server_unexpected_end_of_stream() {
+    for input in [&b"PX 1 2"[..], b"P", b"PB\x01\x00"] {
+        let mut server = PixelflutServerStream::new(CursorPair::new(input), (800, 600));
+        assert_eq!(
+            server.read_pixel().unwrap_err().kind(),
+            PixelflutErrorKind::Io,
+            "{:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn server_line_too_long() {
+    let mut input = b"PX 1 2 ".to_vec();
+    input.resize(64 * 1024, b'0');
+    input.push(b'\n');
+    let mut server = PixelflutServerStream::new(CursorPair::new(&input), (800, 600));
+    assert_eq!(
+        server.read_pixel().unwrap_err().kind(),
+        PixelflutErrorKind::Io
+    );
+}
+
+#[test]
+fn server_invalid_line() {
+    let mut server =
+        PixelflutServerStream::new(CursorPair::new(b"FOO\nPX 1 2 ff0000\n"), (800, 600));
+    assert_eq!(
+        server.read_pixel().unwrap_err().kind(),
+        PixelflutErrorKind::InvalidCommand
+    );
+    assert_eq!(server.get_ref().output(), b"ERROR invalid command\n");
+}
+
+#[test]
+fn server_skip_invalid_lines() {
+    let mut server = PixelflutServerStream::new(
+        CursorPair::new(b"FOO\nPX 1 2 ff0000\nPX 1\nBAR\nPX 3 4 00ff00\n"),
+        (800, 600),
+    );
+    server.set_error_policy(ErrorPolicy::skip().with_max_errors(2));
+    assert_eq!(
+        server.read_pixel().unwrap(),
+        Some(Pixel::from(((1, 2), (255, 0, 0))))
+    );
+    assert_eq!(server.error_count(), 1);
+    assert_eq!(
+        server.read_pixel().unwrap_err().kind(),
+        PixelflutErrorKind::InvalidCommand
+    );
+    assert_eq!(server.error_count(), 3);
+    assert_eq!(
+        server.get_ref().output(),
+        b"ERROR invalid command\nERROR wrong number of arguments\nERROR invalid command\n".as_ref()
+    );
+}
+
+/// Handler recording all callbacks.
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+}
+
+impl PixelflutHandler for Recorder {
+    fn on_connect(&mut self) {
+        self.events.push("connect".to_string());
+    }
+
+    fn on_pixel(&mut self, pixel: Pixel) {
+        self.events.push(format!("pixel {}", pixel));
+    }
+
+    fn on_command(&mut self, command: &Command) -> Option<Response> {
+        self.events.push(format!("command {}", command));
+        match command {
+            Command::Help => Some(Response::Help("custom".into())),
+            _ => None,
+        }
+    }
+
+    fn on_unknown_command(&mut self, verb: &str, args: &[String]) -> Option<Response> {
+        self.events.push(format!("unknown {} {:?}", verb, args));
+        match verb {
+            "PING" => Some(Response::Custom("PONG".into())),
+            _ => None,
+        }
+    }
+
+    fn on_disconnect(&mut self, error: Option<&PixelflutError>) {
+        self.events
+            .push(format!("disconnect {:?}", error.map(|err| err.kind())));
+    }
+}
+
+#[test]
+fn server_run_handler() {
+    let mut server = PixelflutServerStream::new(
+        CursorPair::new(b"OFFSET 1 1\nPX 1 2 ff0000\nPING\r\nHELP\nSIZE\n"),
+        (800, 600),
+    );
+    let mut handler = Recorder::default();
+    server.run_handler(&mut handler).unwrap();
+    assert_eq!(
+        handler.events,
+        vec![
+            "connect",
+            "command OFFSET 1 1",
+            "command PX 1 2 ff0000",
+            "pixel 2 3 ff0000",
+            "command PING",
+            "unknown PING []",
+            "command HELP",
+            "command SIZE",
+            "disconnect None",
+        ]
+    );
+    assert_eq!(
+        server.get_ref().output(),
+        b"PONG\nHELP custom\nHELP\nSIZE 800 600\n".as_ref()
+    );
+}
+
+#[test]
+fn server_run_handler_long_custom_command() {
+    // longer than a `PX` command, but answered by the handler
+    let mut server = PixelflutServerStream::new(
+        CursorPair::new(b"PING the quick brown fox jumps over the lazy dog\n"),
+        (800, 600),
+    );
+    let mut handler = Recorder::default();
+    server.run_handler(&mut handler).unwrap();
+    assert_eq!(server.get_ref().output(), b"PONG\n".as_ref());
+}
+
+#[test]
+fn server_run_handler_invalid_line() {
+    let mut server = PixelflutServerStream::new(CursorPair::new(b"FOO\nSIZE\n"), (800, 600));
+    let mut handler = Recorder::default();
+    assert_eq!(
+        server.run_handler(&mut handler).unwrap_err().kind(),
+        PixelflutErrorKind::InvalidCommand
+    );
+    assert_eq!(
+        handler.events,
+        vec![
+            "connect",
+            "command FOO",
+            "unknown FOO []",
+            "disconnect Some(InvalidCommand)"
+        ]
+    );
+    assert_eq!(server.get_ref().output(), b"ERROR invalid command\n");
+}
+
+#[test]
+fn server_custom_commands() {
+    let mut server = PixelflutServerStream::new(
+        CursorPair::new(
+            b"STATS\nTEXT 1 2 the quick brown fox jumps over the lazy dog\nPX 1 2 ff0000\nCLEAR\n",
+        ),
+        (800, 600),
+    );
+    server.set_error_policy(ErrorPolicy::skip());
+    server.register_command("STATS", |_args, responses| {
+        responses.push(Response::Custom("STATS 1".into()));
+        responses.push(Response::Custom("STATS 2".into()));
+        Ok(())
+    });
+    server.register_command("TEXT", |args, responses| {
+        if args.len() < 3 {
+            return Err(PixelflutErrorKind::WrongNumberOfArguments.into());
+        }
+        responses.push(Response::Custom(
+            format!("TEXT {}", args[2..].join(" ")).into(),
+        ));
+        Ok(())
+    });
+
+    let mut pixels = Vec::new();
+    while server.read_pixels(&mut pixels).unwrap().is_some() {}
+    assert_eq!(pixels, vec![Pixel::from(((1, 2), (255, 0, 0)))]);
+    assert_eq!(server.error_count(), 1);
+    assert_eq!(
+        server.get_ref().output(),
+        b"STATS 1\nSTATS 2\nTEXT the quick brown fox jumps over the lazy dog\nERROR invalid command\n"
+            .as_ref()
+    );
+}
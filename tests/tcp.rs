@@ -0,0 +1,210 @@
+//! Clients and server streams connected over loopback TCP.
+
+#[cfg(feature = "sync")]
+mod sync {
+    use pixelflut::sync::{PixelflutClient, PixelflutServerStream};
+    use pixelflut::{Color, Coordinate, Pixel, PixelflutErrorKind};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::thread;
+
+    fn listen() -> (TcpListener, std::net::SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    fn accept(listener: &TcpListener) -> PixelflutServerStream {
+        let (stream, _) = listener.accept().unwrap();
+        PixelflutServerStream::new(stream, (100, 100))
+    }
+
+    #[test]
+    fn get_pixel_with_offset() {
+        let (listener, addr) = listen();
+        let mut client = PixelflutClient::connect(addr).unwrap();
+        let mut server = accept(&listener);
+        server.set_pixel_lookup(|position| {
+            if position == Coordinate::new(11, 22) {
+                Some(Color::rgb(0x11, 0x22, 0x33))
+            } else {
+                None
+            }
+        });
+        let server = thread::spawn(move || while let Ok(Some(_)) = server.read_pixel() {});
+
+        client.set_offset(10, 20).unwrap();
+        assert_eq!(client.get(1, 2).unwrap(), Color::rgb(0x11, 0x22, 0x33));
+        drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn read_pixels_empty_batch() {
+        let (listener, addr) = listen();
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut server = accept(&listener);
+        client.write_all(b"SIZE\n").unwrap();
+
+        let mut pixels = Vec::new();
+        assert_eq!(server.read_pixels(&mut pixels).unwrap(), Some(0));
+        assert!(pixels.is_empty());
+        let mut response = String::new();
+        BufReader::new(client).read_line(&mut response).unwrap();
+        assert_eq!(response, "SIZE 100 100\n");
+    }
+
+    #[test]
+    fn read_pixels_across_buffer_capacity() {
+        let (listener, addr) = listen();
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut server = accept(&listener);
+        // larger than the 8 KiB buffer of the server stream
+        let expected: Vec<Pixel> = (0..4000)
+            .map(|i| Pixel::new((i % 100, i / 100).into(), (i as u8, 0, 0).into()))
+            .collect();
+        let sent = expected.clone();
+        let writer = thread::spawn(move || {
+            for pixel in sent {
+                writeln!(client, "PX {}", pixel).unwrap();
+            }
+        });
+
+        let mut pixels = Vec::new();
+        while pixels.len() < expected.len() {
+            assert!(server.read_pixels(&mut pixels).unwrap().is_some());
+        }
+        assert_eq!(pixels, expected);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn read_pixels_error_mid_batch() {
+        let (listener, addr) = listen();
+        let mut client = TcpStream::connect(addr).unwrap();
+        let mut server = accept(&listener);
+        client
+            .write_all(b"PX 1 2 ff0000\nPX 3\nPX 4 5 00ff00\n")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let mut pixels = Vec::new();
+        let err = loop {
+            match server.read_pixels(&mut pixels) {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("invalid line was not reported"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), PixelflutErrorKind::WrongNumberOfArguments);
+        assert_eq!(pixels, [Pixel::new((1, 2).into(), (255, 0, 0).into())]);
+    }
+}
+
+#[cfg(feature = "tokio-rt")]
+mod async_tokio {
+    use pixelflut::async_tokio::{PixelflutClient, PixelflutServerStream};
+    use pixelflut::{Color, Coordinate, Pixel, PixelflutErrorKind};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn listen() -> (TcpListener, std::net::SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    #[tokio::test]
+    async fn get_pixel_with_offset() {
+        let (listener, addr) = listen().await;
+        let mut client = PixelflutClient::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = PixelflutServerStream::new(stream, (100, 100));
+        server.set_pixel_lookup(|position| {
+            if position == Coordinate::new(11, 22) {
+                Some(Color::rgb(0x11, 0x22, 0x33))
+            } else {
+                None
+            }
+        });
+        let server =
+            tokio::spawn(async move { while let Ok(Some(_)) = server.read_pixel().await {} });
+
+        client.set_offset(10, 20).await.unwrap();
+        assert_eq!(
+            client.get(1, 2).await.unwrap(),
+            Color::rgb(0x11, 0x22, 0x33)
+        );
+        drop(client);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_pixels_empty_batch() {
+        let (listener, addr) = listen().await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = PixelflutServerStream::new(stream, (100, 100));
+        client.write_all(b"SIZE\n").await.unwrap();
+
+        let mut pixels = Vec::new();
+        assert_eq!(server.read_pixels(&mut pixels).await.unwrap(), Some(0));
+        assert!(pixels.is_empty());
+        let mut response = String::new();
+        let mut client = BufReader::new(client);
+        client.read_line(&mut response).await.unwrap();
+        assert_eq!(response, "SIZE 100 100\n");
+
+        client.get_mut().shutdown().await.unwrap();
+        assert_eq!(server.read_pixels(&mut pixels).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn read_pixels_across_buffer_capacity() {
+        let (listener, addr) = listen().await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        // lines do not line up with the end of the small buffer
+        let mut server = PixelflutServerStream::with_capacity(stream, (100, 100), 50);
+        let expected: Vec<Pixel> = (0..1000)
+            .map(|i| Pixel::new((i % 100, i / 100).into(), (i as u8, 0, 0).into()))
+            .collect();
+        let sent = expected.clone();
+        let writer = tokio::spawn(async move {
+            for pixel in sent {
+                let line = format!("PX {}\n", pixel);
+                client.write_all(line.as_bytes()).await.unwrap();
+            }
+            client.shutdown().await.unwrap();
+        });
+
+        let mut pixels = Vec::new();
+        while let Some(_count) = server.read_pixels(&mut pixels).await.unwrap() {}
+        assert_eq!(pixels, expected);
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_pixels_error_mid_batch() {
+        let (listener, addr) = listen().await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut server = PixelflutServerStream::new(stream, (100, 100));
+        client
+            .write_all(b"PX 1 2 ff0000\nPX 3\nPX 4 5 00ff00\n")
+            .await
+            .unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut pixels = Vec::new();
+        let err = loop {
+            match server.read_pixels(&mut pixels).await {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("invalid line was not reported"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), PixelflutErrorKind::WrongNumberOfArguments);
+        assert_eq!(pixels, [Pixel::new((1, 2).into(), (255, 0, 0).into())]);
+    }
+}